@@ -411,6 +411,8 @@ const ALLOWLIST_FN: &'static [&'static str] = &[
     "GLSLangInitialize",
     "GLSLangFinalize",
     "GLSLangInitBuiltInResources",
+    "GLSLangSetMaxComputeWorkGroupCount",
+    "GLSLangSetMaxComputeWorkGroupSize",
     "GLSLangConstructCompiler",
     "GLSLangDestructCompiler",
     "GLSLangCompile",
@@ -420,7 +422,44 @@ const ALLOWLIST_FN: &'static [&'static str] = &[
     "GLSLangGetObjectCode",
     "GLSLangGetInfoLog",
     "GLSLangIterUniformNameMapping",
+    "GLSLangIterNameHashingMap",
+    "GLSLangIterExtensionBehavior",
+    "GLSLangGetExtensionDirectives",
     "GLSLangGetNumUnpackedVaryingVectors",
+    "GLSLangGetTypePackingInfo",
+    "GLSLangCheckVariablesWithinPackingLimits",
+    "GLSLangGetComputeShaderLocalGroupSize",
+    "GLSLangGetShaderSharedMemorySize",
+    "GLSLangGetGeometryShaderInputPrimitiveType",
+    "GLSLangGetGeometryShaderOutputPrimitiveType",
+    "GLSLangGetGeometryShaderInvocations",
+    "GLSLangGetGeometryShaderMaxVertices",
+    "GLSLangGetTessControlShaderVertices",
+    "GLSLangGetTessGenMode",
+    "GLSLangGetTessGenSpacing",
+    "GLSLangGetTessGenVertexOrder",
+    "GLSLangHasTessGenPointMode",
+    "GLSLangIterUniformRegisterMap",
+    "GLSLangGetUniformBlockRegister",
+    "GLSLangGetShaderStorageBlockRegister",
+    "GLSLangShouldUniformBlockUseStructuredBuffer",
+    "GLSLangIterSlowCompilingUniformBlocks",
+    "GLSLangGetImage2DRegisterIndex",
+    "GLSLangGetReadonlyImage2DRegisterIndex",
+    "GLSLangIterUniforms",
+    "GLSLangIterAttributes",
+    "GLSLangIterVaryings",
+    "GLSLangIterInputVaryings",
+    "GLSLangIterOutputVaryings",
+    "GLSLangIterOutputVariables",
+    "GLSLangIterInterfaceBlocks",
+    "GLSLangIterUniformBlocks",
+    "GLSLangIterShaderStorageBlocks",
+    "GLSLangGetInterfaceBlockLayout",
+    "GLSLangIsSameUniformAtLinkTime",
+    "GLSLangIsSameVaryingAtLinkTime",
+    "GLSLangIsSameInterfaceBlockAtLinkTime",
+    "GLSLangPreprocess",
 ];
 
 /// Make a path relative to the working directory that is used for the build.
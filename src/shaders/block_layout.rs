@@ -0,0 +1,98 @@
+use super::ffi::GLSLangGetInterfaceBlockLayout;
+use super::variables::flatten;
+use super::{BlockLayout, InterfaceBlock};
+
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_int, c_void};
+
+/// Where a member of an interface block lives in the buffer backing it, as
+/// reported by `UNIFORM_OFFSET`, `UNIFORM_ARRAY_STRIDE` and friends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockMemberInfo {
+    /// Byte offset from the start of the block.
+    pub offset: i32,
+    /// Bytes between array elements, or 0 for members that are not arrays.
+    pub array_stride: i32,
+    /// Bytes between matrix columns, or rows for row-major matrices, or 0
+    /// for members that are not matrices.
+    pub matrix_stride: i32,
+    pub is_row_major_matrix: bool,
+    /// Bytes between elements of the top-level array a member of an array
+    /// of structs belongs to, as reported by `TOP_LEVEL_ARRAY_STRIDE`.
+    pub top_level_array_stride: i32,
+}
+
+/// The layout of every member of an interface block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockLayoutInfo {
+    /// Members by their GL API name, for example `Block.lights[0].position`.
+    /// Arrays of basic types have a single entry named after the array, and
+    /// opaque members are left out.
+    pub members: HashMap<String, BlockMemberInfo>,
+    /// The size of a single element of the block in bytes, as reported by
+    /// `UNIFORM_BLOCK_DATA_SIZE`. For blocks ending with an unsized array,
+    /// this is the size with one array element.
+    pub size: usize,
+}
+
+impl InterfaceBlock {
+    /// Lays out the block's fields according to its layout qualifier.
+    ///
+    /// `packed` and `shared` blocks are laid out as `std140`, as ANGLE does
+    /// on backends without a driver to ask.
+    pub fn compute_layout(&self) -> BlockLayoutInfo {
+        self.compute_layout_as(self.layout)
+    }
+
+    /// Lays out the block's fields as if it had been declared with `layout`,
+    /// using the same encoder as the translator.
+    pub fn compute_layout_as(&self, layout: BlockLayout) -> BlockLayoutInfo {
+        unsafe extern "C" fn each_c(
+            closure: *mut c_void,
+            name: *const c_char,
+            name_len: usize,
+            offset: c_int,
+            array_stride: c_int,
+            matrix_stride: c_int,
+            is_row_major_matrix: c_int,
+            top_level_array_stride: c_int,
+        ) {
+            let members = &mut *(closure as *mut HashMap<String, BlockMemberInfo>);
+            members.insert(
+                super::to_string(name, name_len),
+                BlockMemberInfo {
+                    offset,
+                    array_stride,
+                    matrix_stride,
+                    is_row_major_matrix: is_row_major_matrix != 0,
+                    top_level_array_stride,
+                },
+            );
+        }
+
+        // Fields of blocks with an instance name are queried through the
+        // block name, see `sh::InterfaceBlock::fieldPrefix`.
+        let prefix = if self.instance_name.is_empty() {
+            ""
+        } else {
+            &*self.name
+        };
+
+        let mut fields = Vec::new();
+        flatten(&self.fields, &mut fields);
+
+        let mut members = HashMap::new();
+        let size = unsafe {
+            GLSLangGetInterfaceBlockLayout(
+                fields.as_ptr(),
+                fields.len(),
+                prefix.as_ptr() as *const c_char,
+                prefix.len(),
+                (layout == BlockLayout::Std430) as c_int,
+                Some(each_c),
+                &mut members as *mut HashMap<String, BlockMemberInfo> as *mut c_void,
+            )
+        };
+        BlockLayoutInfo { members, size }
+    }
+}
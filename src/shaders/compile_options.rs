@@ -0,0 +1,302 @@
+use super::ffi::ShCompileOptions;
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+
+/// Options passed to `ShaderValidator::compile`.
+///
+/// Every flag of ANGLE's `ShCompileOptions` has a builder method of the same
+/// name in snake case. The raw struct is reachable through `Deref`, for its
+/// Metal and pixel local storage settings.
+#[derive(Clone, Copy)]
+pub struct CompileOptions(ShCompileOptions);
+
+macro_rules! compile_options {
+    ($($(#[$doc:meta])* $method:ident => $getter:ident, $setter:ident;)*) => {
+        impl CompileOptions {
+            $(
+                $(#[$doc])*
+                #[inline]
+                pub fn $method(mut self, enabled: bool) -> Self {
+                    self.0.$setter(enabled as _);
+                    self
+                }
+            )*
+
+            /// The names of the enabled flags, in declaration order.
+            pub fn enabled_flags(&self) -> Vec<&'static str> {
+                let mut flags = vec![];
+                $(
+                    if self.0.$getter() != 0 {
+                        flags.push(stringify!($method));
+                    }
+                )*
+                flags
+            }
+
+            /// One bit per flag, in declaration order.
+            fn bits(&self) -> u64 {
+                let mut bits = 0;
+                let mut bit = 0;
+                $(
+                    bits |= (self.0.$getter() as u64 & 1) << bit;
+                    bit += 1;
+                )*
+                let _ = bit;
+                bits
+            }
+        }
+    };
+}
+
+compile_options! {
+    /// Translate the shader so that `object_code()` returns the result.
+    object_code => objectCode, set_objectCode;
+    /// Collect attributes, uniforms, varyings and interface blocks for reflection.
+    variables => variables, set_variables;
+    /// Track the source path of the shader.
+    source_path => sourcePath, set_sourcePath;
+    /// Write the AST to the info log.
+    intermediate_tree => intermediateTree, set_intermediateTree;
+    /// Validate the AST after every transformation. Useful for debugging.
+    validate_ast => validateAST, set_validateAST;
+    /// Enforce the loop and indexing limits of GLSL ES 1.00 Appendix A. Implied for WebGL.
+    validate_loop_indexing => validateLoopIndexing, set_validateLoopIndexing;
+    /// Emit `#line` directives in the output.
+    line_directives => lineDirectives, set_lineDirectives;
+    /// Drop `invariant` and `centroid` from ESSL3 shaders translated to older desktop GLSL.
+    remove_invariant_and_centroid_for_essl3 => removeInvariantAndCentroidForESSL3, set_removeInvariantAndCentroidForESSL3;
+    /// Work around `abs(int)` bugs in Intel Mac drivers.
+    emulate_abs_int_function => emulateAbsIntFunction, set_emulateAbsIntFunction;
+    /// Enforce the GLSL ES 1.00 Appendix A uniform packing restrictions.
+    enforce_packing_restrictions => enforcePackingRestrictions, set_enforcePackingRestrictions;
+    /// Clamp all indirect array indexing to the bounds of the array.
+    clamp_indirect_array_bounds => clampIndirectArrayBounds, set_clampIndirectArrayBounds;
+    /// Reject expressions more complex than `MaxExpressionComplexity`.
+    limit_expression_complexity => limitExpressionComplexity, set_limitExpressionComplexity;
+    /// Reject call stacks deeper than `MaxCallStackDepth`.
+    limit_call_stack_depth => limitCallStackDepth, set_limitCallStackDepth;
+    /// Initialize `gl_Position` to zero at the start of a vertex shader's `main()`.
+    init_gl_position => initGLPosition, set_initGLPosition;
+    /// Initialize `gl_PointSize` to zero at the start of a vertex shader's `main()`.
+    init_gl_point_size => initGLPointSize, set_initGLPointSize;
+    /// Rewrite `&&` and `||` as ternaries to work around Mac drivers.
+    unfold_short_circuit => unfoldShortCircuit, set_unfoldShortCircuit;
+    /// Initialize output variables to zero at the start of `main()`.
+    init_output_variables => initOutputVariables, set_initOutputVariables;
+    /// Scalarize vector and matrix constructor arguments.
+    scalarize_vec_and_mat_constructor_args => scalarizeVecAndMatConstructorArgs, set_scalarizeVecAndMatConstructorArgs;
+    /// Give struct names a unique prefix for drivers with broken struct scoping.
+    regenerate_struct_names => regenerateStructNames, set_regenerateStructNames;
+    /// Rewrite do-while loops to work around Mac drivers.
+    rewrite_do_while_loops => rewriteDoWhileLoops, set_rewriteDoWhileLoops;
+    /// Expand integer `pow` expressions into multiplies in HLSL output.
+    expand_select_hlsl_integer_pow_expressions => expandSelectHLSLIntegerPowExpressions, set_expandSelectHLSLIntegerPowExpressions;
+    /// Flatten `#pragma STDGL invariant(all)` into the declarations it affects.
+    flatten_pragma_stdgl_invariant_all => flattenPragmaSTDGLInvariantAll, set_flattenPragmaSTDGLInvariantAll;
+    /// Account for the base level when emulating `textureSize` in HLSL.
+    hlsl_get_dimensions_ignores_base_level => HLSLGetDimensionsIgnoresBaseLevel, set_HLSLGetDimensionsIgnoresBaseLevel;
+    /// Rewrite `texelFetchOffset` as `texelFetch` for Intel drivers.
+    rewrite_texel_fetch_offset_to_texel_fetch => rewriteTexelFetchOffsetToTexelFetch, set_rewriteTexelFetchOffsetToTexelFetch;
+    /// Append `&& true` to loop conditions for Intel Mac drivers.
+    add_and_true_to_loop_condition => addAndTrueToLoopCondition, set_addAndTrueToLoopCondition;
+    /// Rewrite integer unary minus for Intel drivers.
+    rewrite_integer_unary_minus_operator => rewriteIntegerUnaryMinusOperator, set_rewriteIntegerUnaryMinusOperator;
+    /// Emulate `isnan()` for Intel D3D and Mac drivers.
+    emulate_isnan_float_function => emulateIsnanFloatFunction, set_emulateIsnanFloatFunction;
+    /// Reference members of unused std140 and shared uniform blocks so they stay active.
+    use_unused_standard_shared_blocks => useUnusedStandardSharedBlocks, set_useUnusedStandardSharedBlocks;
+    /// Rewrite float unary minus for Intel Mac drivers.
+    rewrite_float_unary_minus_operator => rewriteFloatUnaryMinusOperator, set_rewriteFloatUnaryMinusOperator;
+    /// Emulate `atan(y, x)` for NVIDIA OpenGL drivers.
+    emulate_atan2_float_function => emulateAtan2FloatFunction, set_emulateAtan2FloatFunction;
+    /// Initialize uninitialized local and global temporaries.
+    initialize_uninitialized_locals => initializeUninitializedLocals, set_initializeUninitializedLocals;
+    /// Emulate `OVR_multiview` built-ins with instancing.
+    initialize_builtins_for_instanced_multiview => initializeBuiltinsForInstancedMultiview, set_initializeBuiltinsForInstancedMultiview;
+    /// Select the viewport or layer in the vertex shader for instanced multiview.
+    select_view_in_nv_glsl_vertex_shader => selectViewInNvGLSLVertexShader, set_selectViewInNvGLSLVertexShader;
+    /// Clamp `gl_PointSize` to `MaxPointSize`.
+    clamp_point_size => clampPointSize, set_clampPointSize;
+    /// Emulate `KHR_blend_equation_advanced` in the fragment shader.
+    add_advanced_blend_equations_emulation => addAdvancedBlendEquationsEmulation, set_addAdvancedBlendEquationsEmulation;
+    /// Initialize variables without loops.
+    dont_use_loops_to_initialize_variables => dontUseLoopsToInitializeVariables, set_dontUseLoopsToInitializeVariables;
+    /// Don't allocate uniforms in D3D constant register zero.
+    skip_d3d_constant_register_zero => skipD3DConstantRegisterZero, set_skipD3DConstantRegisterZero;
+    /// Clamp `gl_FragDepth` to [0, 1] if it is statically used.
+    clamp_frag_depth => clampFragDepth, set_clampFragDepth;
+    /// Rewrite chained assignments to swizzles such as `v.x = z = e;`.
+    rewrite_repeated_assign_to_swizzled => rewriteRepeatedAssignToSwizzled, set_rewriteRepeatedAssignToSwizzled;
+    /// Rewrite `gl_DrawID` as a uniform.
+    emulate_gl_draw_id => emulateGLDrawID, set_emulateGLDrawID;
+    /// Initialize `shared` variables to zero.
+    init_shared_variables => initSharedVariables, set_initSharedVariables;
+    /// Always resolve the value returned by atomic operations.
+    force_atomic_value_resolution => forceAtomicValueResolution, set_forceAtomicValueResolution;
+    /// Rewrite `gl_BaseVertex` and `gl_BaseInstance` as uniforms.
+    emulate_gl_base_vertex_base_instance => emulateGLBaseVertexBaseInstance, set_emulateGLBaseVertexBaseInstance;
+    /// Emulate seamful cube map sampling for OpenGL ES 2.0.
+    emulate_seamful_cube_map_sampling => emulateSeamfulCubeMapSampling, set_emulateSeamfulCubeMapSampling;
+    /// Translate `WEBGL_video_texture` samplers as `samplerExternalOES`.
+    take_video_texture_as_external_oes => takeVideoTextureAsExternalOES, set_takeVideoTextureAsExternalOES;
+    /// Add the base vertex to `gl_VertexID` for Mac AMD drivers.
+    add_base_vertex_to_vertex_id => addBaseVertexToVertexID, set_addBaseVertexToVertexID;
+    /// Remove dynamic lvalue indexing of swizzled vectors.
+    remove_dynamic_indexing_of_swizzled_vector => removeDynamicIndexingOfSwizzledVector, set_removeDynamicIndexingOfSwizzledVector;
+    /// Allow uniform blocks to become `StructuredBuffer`s in HLSL for faster FXC compiles.
+    allow_translate_uniform_block_to_structured_buffer => allowTranslateUniformBlockToStructuredBuffer, set_allowTranslateUniformBlockToStructuredBuffer;
+    /// Emit `layout(yuv)` decorations for Vulkan.
+    add_vulkan_yuv_layout_qualifier => addVulkanYUVLayoutQualifier, set_addVulkanYUVLayoutQualifier;
+    /// Disable `ARB_texture_rectangle` for this compile.
+    disable_arb_texture_rectangle => disableARBTextureRectangle, set_disableARBTextureRectangle;
+    /// Rewrite row-major matrices as column-major.
+    rewrite_row_major_matrices => rewriteRowMajorMatrices, set_rewriteRowMajorMatrices;
+    /// Drop explicit precision qualifiers.
+    ignore_precision_qualifiers => ignorePrecisionQualifiers, set_ignorePrecisionQualifiers;
+    /// Correct depth for the Vulkan clip space.
+    add_vulkan_depth_correction => addVulkanDepthCorrection, set_addVulkanDepthCorrection;
+    /// Force `highp` to `mediump`.
+    force_shader_precision_highp_to_mediump => forceShaderPrecisionHighpToMediump, set_forceShaderPrecisionHighpToMediump;
+    /// Use specialization constants for pre-rotation and y-flip.
+    use_specialization_constant => useSpecializationConstant, set_useSpecializationConstant;
+    /// Emit Vulkan transform feedback emulation code.
+    add_vulkan_xfb_emulation_support_code => addVulkanXfbEmulationSupportCode, set_addVulkanXfbEmulationSupportCode;
+    /// Emit support code for `VK_EXT_transform_feedback`.
+    add_vulkan_xfb_extension_support_code => addVulkanXfbExtensionSupportCode, set_addVulkanXfbExtensionSupportCode;
+    /// Initialize fragment outputs to zero at the start of `main()`.
+    init_fragment_output_variables => initFragmentOutputVariables, set_initFragmentOutputVariables;
+    /// Produce SPIR-V through glslang instead of directly.
+    generate_spirv_through_glslang => generateSpirvThroughGlslang, set_generateSpirvThroughGlslang;
+    /// Insert explicit bool casts for Intel drivers on macOS 10.15.
+    add_explicit_bool_casts => addExplicitBoolCasts, set_addExplicitBoolCasts;
+    /// Round outputs after applying dither.
+    round_output_after_dithering => roundOutputAfterDithering, set_roundOutputAfterDithering;
+    /// Make `x / x` return exactly 1.0.
+    precision_safe_division => precisionSafeDivision, set_precisionSafeDivision;
+    /// Pass `highp` arguments to `packUnorm*`/`packSnorm*`.
+    pass_highp_to_pack_unorm_snorm_builtins => passHighpToPackUnormSnormBuiltins, set_passHighpToPackUnormSnormBuiltins;
+    /// Check uniform block counts against the per-stage `Max*UniformBlocks` limits.
+    validate_per_stage_max_uniform_blocks => validatePerStageMaxUniformBlocks, set_validatePerStageMaxUniformBlocks;
+}
+
+impl CompileOptions {
+    /// Options with every flag disabled.
+    pub fn new() -> Self {
+        CompileOptions(unsafe { ShCompileOptions::new() })
+    }
+
+    /// The options used by `ShaderValidator::compile_and_translate`.
+    pub fn mozangle() -> Self {
+        // Todo(Mortimer): Add SH_TIMING_RESTRICTIONS to options when the implementations gets better
+        // Right now SH_TIMING_RESTRICTIONS is experimental
+        // and doesn't support user callable functions in shaders
+        CompileOptions::new()
+            .object_code(true)
+            .variables(true) // For uniform_name_map()
+            .emulate_abs_int_function(true) // To workaround drivers
+            .emulate_isnan_float_function(true) // To workaround drivers
+            .emulate_atan2_float_function(true) // To workaround drivers
+            .clamp_indirect_array_bounds(true)
+            .init_gl_position(true)
+            .enforce_packing_restrictions(true)
+            .limit_expression_complexity(true)
+            .limit_call_stack_depth(true)
+    }
+
+    /// `mozangle()` plus everything WebGL requires to keep shaders from
+    /// reading uninitialized memory.
+    pub fn strict_webgl() -> Self {
+        CompileOptions::mozangle()
+            .validate_loop_indexing(true)
+            .init_output_variables(true)
+            .initialize_uninitialized_locals(true)
+            .init_shared_variables(true)
+            .clamp_point_size(true)
+            .clamp_frag_depth(true)
+    }
+
+    /// `mozangle()` plus the workarounds ANGLE applies for bugs in desktop
+    /// OpenGL drivers.
+    pub fn desktop_driver_workarounds() -> Self {
+        CompileOptions::mozangle()
+            .init_gl_point_size(true)
+            .unfold_short_circuit(true)
+            .scalarize_vec_and_mat_constructor_args(true)
+            .regenerate_struct_names(true)
+            .rewrite_do_while_loops(true)
+            .rewrite_texel_fetch_offset_to_texel_fetch(true)
+            .add_and_true_to_loop_condition(true)
+            .rewrite_integer_unary_minus_operator(true)
+            .rewrite_float_unary_minus_operator(true)
+            .dont_use_loops_to_initialize_variables(true)
+            .rewrite_repeated_assign_to_swizzled(true)
+            .remove_dynamic_indexing_of_swizzled_vector(true)
+    }
+
+    /// `mozangle()` plus AST validation after every transformation and the
+    /// AST dump in the info log.
+    pub fn debug() -> Self {
+        CompileOptions::mozangle()
+            .validate_ast(true)
+            .intermediate_tree(true)
+    }
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions::new()
+    }
+}
+
+impl From<ShCompileOptions> for CompileOptions {
+    fn from(options: ShCompileOptions) -> Self {
+        CompileOptions(options)
+    }
+}
+
+impl Deref for CompileOptions {
+    type Target = ShCompileOptions;
+
+    fn deref(&self) -> &ShCompileOptions {
+        &self.0
+    }
+}
+
+impl DerefMut for CompileOptions {
+    fn deref_mut(&mut self) -> &mut ShCompileOptions {
+        &mut self.0
+    }
+}
+
+impl fmt::Debug for CompileOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.enabled_flags()).finish()
+    }
+}
+
+impl PartialEq for CompileOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+            && self.0.metal.driverUniformsBindingIndex == other.0.metal.driverUniformsBindingIndex
+            && self.0.metal.defaultUniformsBindingIndex == other.0.metal.defaultUniformsBindingIndex
+            && self.0.metal.UBOArgumentBufferBindingIndex
+                == other.0.metal.UBOArgumentBufferBindingIndex
+            && self.0.pls.type_ == other.0.pls.type_
+            && self.0.pls.fragmentSynchronizationType == other.0.pls.fragmentSynchronizationType
+    }
+}
+
+impl Eq for CompileOptions {}
+
+impl Hash for CompileOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+        self.0.metal.driverUniformsBindingIndex.hash(state);
+        self.0.metal.defaultUniformsBindingIndex.hash(state);
+        self.0.metal.UBOArgumentBufferBindingIndex.hash(state);
+        self.0.pls.type_.hash(state);
+        self.0.pls.fragmentSynchronizationType.hash(state);
+    }
+}
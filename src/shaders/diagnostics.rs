@@ -0,0 +1,125 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// How serious a diagnostic reported by the translator is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single message from ANGLE's info log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Index of the source string passed to `compile` the message refers to,
+    /// or `None` for messages about the shader as a whole.
+    pub source_string: Option<u32>,
+    /// The name of that source string, for shaders compiled with
+    /// `ShaderValidator::compile_named`.
+    pub source_name: Option<String>,
+    /// One-based line number inside that source string, if known.
+    pub line: Option<u32>,
+    /// The token the message is about, if any.
+    pub token: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats the diagnostic the same way ANGLE writes it to the info log.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Error => f.write_str("ERROR: ")?,
+            Severity::Warning => f.write_str("WARNING: ")?,
+        }
+        if let Some(source_string) = self.source_string {
+            match self.source_name {
+                Some(ref name) => f.write_str(name)?,
+                None => write!(f, "{}", source_string)?,
+            }
+            match self.line {
+                Some(line) => write!(f, ":{}: ", line)?,
+                None => f.write_str(":? : ")?,
+            }
+            write!(f, "'{}' : ", self.token.as_deref().unwrap_or(""))?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Parses an info log into individual messages.
+///
+/// This understands every shape written by the translator's `TDiagnostics`,
+/// which also reports the preprocessor's diagnostics:
+///
+/// * `ERROR: 0:3: 'foo' : undeclared identifier`
+/// * `WARNING: 1:7: 'GL_OES_foo' : extension is not supported`
+/// * `ERROR: 0:? : 'foo' : message`, when the line is unknown
+/// * `ERROR: 0:5: '' : message`, for `#error` and messages without a token
+/// * `ERROR: Missing main()`, for messages about the whole shader
+///
+/// Lines that are not diagnostics, such as the AST dump written by the
+/// `intermediateTree` option, are skipped.
+pub fn parse_info_log(log: &str) -> Vec<Diagnostic> {
+    log.lines().filter_map(parse_line).collect()
+}
+
+pub(super) fn parse_line(line: &str) -> Option<Diagnostic> {
+    let (severity, rest) = if let Some(rest) = line.strip_prefix("ERROR: ") {
+        (Severity::Error, rest)
+    } else if let Some(rest) = line.strip_prefix("WARNING: ") {
+        (Severity::Warning, rest)
+    } else if let Some(rest) = line.strip_prefix("UNKOWN ERROR: ") {
+        // Sic, see `TInfoSinkBase::prefix`.
+        (Severity::Error, rest)
+    } else {
+        return None;
+    };
+
+    // Located messages look like `0:3: 'token' : message`, anything else is a
+    // global message about the whole shader. The token is searched for the
+    // first closing quote followed by ` : ` so that tokens which are
+    // themselves a quote, and messages which quote types, both work.
+    if let Some((source_string, line, rest)) = parse_location(rest) {
+        if let Some(rest) = rest.strip_prefix('\'') {
+            if let Some(end) = rest.find("' : ") {
+                let token = &rest[..end];
+                return Some(Diagnostic {
+                    severity,
+                    source_string: Some(source_string),
+                    source_name: None,
+                    line,
+                    token: if token.is_empty() {
+                        None
+                    } else {
+                        Some(token.to_owned())
+                    },
+                    message: rest[end + 4..].to_owned(),
+                });
+            }
+        }
+    }
+
+    Some(Diagnostic {
+        severity,
+        source_string: None,
+        source_name: None,
+        line: None,
+        token: None,
+        message: rest.to_owned(),
+    })
+}
+
+/// Parses the `file:line: ` prefix written by `TInfoSinkBase::location`. A
+/// line of zero is written as `file:? : `.
+fn parse_location(s: &str) -> Option<(u32, Option<u32>, &str)> {
+    let colon = s.find(':')?;
+    let source_string = u32::from_str(&s[..colon]).ok()?;
+    let s = &s[colon + 1..];
+    if let Some(rest) = s.strip_prefix("? : ") {
+        return Some((source_string, None, rest));
+    }
+    let end = s.find(": ")?;
+    let line = u32::from_str(&s[..end]).ok()?;
+    Some((source_string, Some(line), &s[end + 2..]))
+}
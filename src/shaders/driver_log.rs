@@ -0,0 +1,144 @@
+use super::{source_map, NameMap, ShaderValidator};
+
+use std::collections::HashMap;
+
+impl ShaderValidator {
+    /// Rewrites the log that a native compiler or driver produced for the
+    /// object code of the last compiled shader so that it refers to the
+    /// original shader.
+    ///
+    /// Identifiers found in `name_map` or `name_hashing_map` are replaced
+    /// with the original ones, and others are left alone. The HLSL outputs
+    /// don't use those mapped names, but prefix the original ones with an
+    /// underscore, so that is what is looked for instead.
+    ///
+    /// For shaders compiled with `compile_with_source_map`, the location
+    /// that starts each line of a D3D compiler log, such as
+    /// `C:\fakepath(12,5-9): error X3004: ...`, is replaced with the source
+    /// string, or its name, and the line in it. The D3D compiler follows the
+    /// `#line` directives of the object code. GL drivers number the lines of
+    /// the object code itself, which has no such directives for the GLSL and
+    /// ESSL outputs, so their locations are left as they are.
+    pub fn translate_driver_log(&self, log: &str) -> String {
+        let (name_map, hashed_names) = (self.name_map(), self.name_hashing_map());
+        let names = Identifiers::new(&name_map, &hashed_names, self.output.is_hlsl());
+        let line_starts = self.line_starts.borrow();
+        let source_names = self.source_names.borrow();
+
+        let mut translated = String::with_capacity(log.len());
+        for (i, line) in log.split('\n').enumerate() {
+            if i > 0 {
+                translated.push('\n');
+            }
+            let message = match location(line) {
+                Some((number, columns, len)) if !line_starts.is_empty() => {
+                    let location = source_map::locate(&line_starts, number);
+                    match source_names.get(location.string as usize) {
+                        Some(name) => translated.push_str(name),
+                        None => translated.push_str(&location.string.to_string()),
+                    }
+                    translated.push('(');
+                    translated.push_str(&location.line.to_string());
+                    translated.push_str(columns);
+                    translated.push(')');
+                    &line[len..]
+                }
+                _ => line,
+            };
+            names.translate(message, &mut translated);
+        }
+        translated
+    }
+}
+
+/// Maps identifiers of the object code back to the original shader.
+struct Identifiers<'a> {
+    names: HashMap<String, &'a str>,
+    map: &'a NameMap,
+}
+
+impl<'a> Identifiers<'a> {
+    fn new(map: &'a NameMap, hashed: &'a HashMap<String, String>, hlsl: bool) -> Self {
+        let mut pairs = Vec::new();
+        for (name, mapped_name) in map.iter() {
+            let parts = name.split('.');
+            let mapped_parts = mapped_name.split('.');
+            if parts.clone().count() == mapped_parts.clone().count() {
+                pairs.extend(mapped_parts.zip(parts));
+            }
+        }
+        for (name, hashed_name) in hashed {
+            pairs.push((&**hashed_name, &**name));
+        }
+
+        let names = pairs
+            .into_iter()
+            .map(|(mapped_name, name)| {
+                if hlsl && !name.starts_with("gl_") {
+                    // See `Decorate` in UtilsHLSL.cpp.
+                    (format!("_{}", name), name)
+                } else {
+                    (mapped_name.to_owned(), name)
+                }
+            })
+            .collect();
+        Identifiers { names, map }
+    }
+
+    fn original<'b>(&'b self, identifier: &'b str) -> &'b str {
+        if let Some(name) = self.names.get(identifier) {
+            return name;
+        }
+        self.map
+            .original_struct_name(identifier)
+            .unwrap_or(identifier)
+    }
+
+    /// Appends `text` to `translated` with its identifiers translated.
+    fn translate(&self, text: &str, translated: &mut String) {
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let len = if c.is_ascii_alphabetic() || c == '_' {
+                let len = identifier_len(rest);
+                translated.push_str(self.original(&rest[..len]));
+                len
+            } else if c.is_ascii_digit() {
+                // Keeps suffixes such as the `f` of `1.0f` apart from
+                // identifiers.
+                let len = identifier_len(rest);
+                translated.push_str(&rest[..len]);
+                len
+            } else {
+                translated.push(c);
+                c.len_utf8()
+            };
+            rest = &rest[len..];
+        }
+    }
+}
+
+fn identifier_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(s.len())
+}
+
+/// Parses the `file(line,columns): ` prefix of a D3D compiler message,
+/// returning the line, the rest of the parenthesized text and the length of
+/// the prefix up to the closing parenthesis.
+fn location(line: &str) -> Option<(u32, &str, usize)> {
+    let end = line.find("): ")?;
+    let open = line[..end].rfind('(')?;
+    if line[..open].contains(": ") {
+        return None;
+    }
+    let inside = &line[open + 1..end];
+    let number_len = inside
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(inside.len());
+    let number = inside[..number_len].parse().ok()?;
+    let columns = &inside[number_len..];
+    if !columns.is_empty() && !columns.starts_with(',') {
+        return None;
+    }
+    Some((number, columns, end + 1))
+}
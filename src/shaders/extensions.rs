@@ -0,0 +1,170 @@
+use super::ffi::{GLSLangGetExtensionDirectives, GLSLangIterExtensionBehavior};
+use super::{source_map, ShaderValidator, SourceLocation};
+
+use std::collections::BTreeMap;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+/// The behavior of an extension, as set by `#extension name : behavior`.
+/// The variants follow ANGLE's `TBehavior`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExtensionBehavior {
+    Require,
+    Enable,
+    Warn,
+    Disable,
+    /// No directive named the extension.
+    Undefined,
+}
+
+impl ExtensionBehavior {
+    /// Parses the behavior of an `#extension` directive.
+    pub fn parse(behavior: &str) -> Option<Self> {
+        match behavior {
+            "require" => Some(ExtensionBehavior::Require),
+            "enable" => Some(ExtensionBehavior::Enable),
+            "warn" => Some(ExtensionBehavior::Warn),
+            "disable" => Some(ExtensionBehavior::Disable),
+            _ => None,
+        }
+    }
+
+    /// Whether the extension can be used, see `IsExtensionEnabled`.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            ExtensionBehavior::Require | ExtensionBehavior::Enable | ExtensionBehavior::Warn => {
+                true
+            }
+            ExtensionBehavior::Disable | ExtensionBehavior::Undefined => false,
+        }
+    }
+
+    fn from_angle(behavior: c_uint) -> Self {
+        match behavior {
+            0 => ExtensionBehavior::Require,
+            1 => ExtensionBehavior::Enable,
+            2 => ExtensionBehavior::Warn,
+            3 => ExtensionBehavior::Disable,
+            _ => ExtensionBehavior::Undefined,
+        }
+    }
+}
+
+/// An `#extension` directive of the last compiled shader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestedExtension {
+    /// The extension name, such as `GL_OES_standard_derivatives`, or `all`.
+    pub name: String,
+    pub behavior: ExtensionBehavior,
+    pub location: SourceLocation,
+    /// The name of the source string of the directive, if it has one.
+    pub source_name: Option<String>,
+    /// Whether ANGLE's directive handler applied the directive, that is
+    /// whether it handled it without a warning or an error. It ignores
+    /// directives such as those naming extensions that the resources don't
+    /// support.
+    pub honoured: bool,
+}
+
+impl ShaderValidator {
+    /// Returns the behavior of each extension supported by the resources, as
+    /// left by the `#extension` directives of the last compilation.
+    ///
+    /// Extensions that the shader didn't mention are `Undefined`, except
+    /// `GL_ARB_texture_rectangle` which is enabled by default. Enabling some
+    /// extensions, such as `GL_OVR_multiview2`, also enables the ones they
+    /// imply.
+    pub fn extension_behavior(&self) -> BTreeMap<String, ExtensionBehavior> {
+        unsafe extern "C" fn each(
+            closure: *mut c_void,
+            name: *const c_char,
+            name_len: usize,
+            behavior: c_uint,
+        ) {
+            let map = &mut *(closure as *mut BTreeMap<String, ExtensionBehavior>);
+            map.insert(
+                super::to_string(name, name_len),
+                ExtensionBehavior::from_angle(behavior),
+            );
+        }
+
+        let mut map = BTreeMap::new();
+        unsafe {
+            GLSLangIterExtensionBehavior(
+                self.handle,
+                Some(each),
+                &mut map as *mut BTreeMap<String, ExtensionBehavior> as *mut c_void,
+            );
+        }
+        map
+    }
+
+    /// Returns the `#extension` directives of the last compiled shader, in
+    /// order, including those naming unsupported extensions.
+    ///
+    /// The directives are found by preprocessing the source strings again
+    /// after the compilation, starting from the extension behavior that the
+    /// compilation started from. Directives in skipped conditional blocks are
+    /// left out, as are those with an invalid behavior.
+    pub fn requested_extensions(&self) -> Vec<RequestedExtension> {
+        self.requested_extensions.borrow().clone()
+    }
+
+    /// Records the `#extension` directives of `strings`, the source strings
+    /// just compiled without the source path, for `requested_extensions`.
+    pub(super) fn record_extension_directives(&self, strings: &[*const c_char]) {
+        type Directives = Vec<(SourceLocation, String, String, bool)>;
+
+        unsafe extern "C" fn each(
+            closure: *mut c_void,
+            file: c_int,
+            line: c_int,
+            name: *const c_char,
+            name_len: usize,
+            behavior: *const c_char,
+            behavior_len: usize,
+            honoured: c_int,
+        ) {
+            let directives = &mut *(closure as *mut Directives);
+            directives.push((
+                SourceLocation {
+                    string: file as u32,
+                    line: line as u32,
+                },
+                super::to_string(name, name_len),
+                super::to_string(behavior, behavior_len),
+                honoured != 0,
+            ));
+        }
+
+        let mut directives = Directives::new();
+        unsafe {
+            GLSLangGetExtensionDirectives(
+                self.handle,
+                strings.as_ptr(),
+                strings.len(),
+                Some(each),
+                &mut directives as *mut Directives as *mut c_void,
+            );
+        }
+
+        let line_starts = self.line_starts.borrow();
+        let source_names = self.source_names.borrow();
+        *self.requested_extensions.borrow_mut() = directives
+            .into_iter()
+            .filter_map(|(location, name, behavior, honoured)| {
+                let location = if line_starts.is_empty() {
+                    location
+                } else {
+                    source_map::locate(&line_starts, location.line)
+                };
+                Some(RequestedExtension {
+                    name,
+                    behavior: ExtensionBehavior::parse(&behavior)?,
+                    location,
+                    source_name: source_names.get(location.string as usize).cloned(),
+                    honoured,
+                })
+            })
+            .collect();
+    }
+}
@@ -1,5 +1,23 @@
 #include "GLSLANG/ShaderLang.h"
 #include "common/utilities.h"
+#include "compiler/preprocessor/DiagnosticsBase.h"
+#include "compiler/preprocessor/DirectiveHandlerBase.h"
+#include "compiler/preprocessor/DirectiveParser.h"
+#include "compiler/preprocessor/Macro.h"
+#include "compiler/preprocessor/MacroExpander.h"
+#include "compiler/preprocessor/Preprocessor.h"
+#include "compiler/preprocessor/Token.h"
+#include "compiler/preprocessor/Tokenizer.h"
+#include "compiler/translator/Compiler.h"
+#include "compiler/translator/Diagnostics.h"
+#include "compiler/translator/DirectiveHandler.h"
+#include "compiler/translator/ExtensionBehavior.h"
+#include "compiler/translator/Initialize.h"
+#include "compiler/translator/VariablePacker.h"
+#include "compiler/translator/blocklayout.h"
+#include "compiler/translator/length_limits.h"
+
+#include <cstring>
 
 extern "C" int GLSLangInitialize()
 {
@@ -20,6 +38,20 @@ extern "C" void GLSLangInitBuiltInResources(ShBuiltInResources *resources)
     sh::InitBuiltInResources(resources);
 }
 
+// The compute work group limits are std::arrays, which bindgen leaves opaque.
+extern "C" void GLSLangSetMaxComputeWorkGroupCount(ShBuiltInResources *resources,
+                                                   const int *count)
+{
+    for (size_t i = 0; i < resources->MaxComputeWorkGroupCount.size(); ++i)
+        resources->MaxComputeWorkGroupCount[i] = count[i];
+}
+
+extern "C" void GLSLangSetMaxComputeWorkGroupSize(ShBuiltInResources *resources, const int *size)
+{
+    for (size_t i = 0; i < resources->MaxComputeWorkGroupSize.size(); ++i)
+        resources->MaxComputeWorkGroupSize[i] = size[i];
+}
+
 extern "C" const char *GLSLangGetBuiltInResourcesString(const ShHandle handle)
 {
     return sh::GetBuiltInResourcesString(handle).c_str();
@@ -81,6 +113,8 @@ extern "C" const char *GLSLangGetObjectCode(const ShHandle handle)
 }
 
 using StrPairFunction = void (*)(void *, const char *, size_t, const char *, size_t);
+using StrFunction = void (*)(void *, const char *, size_t);
+using StrUintFunction = void (*)(void *, const char *, size_t, unsigned int);
 
 extern "C" void GLSLangIterUniformNameMapping(const ShHandle handle, StrPairFunction each, void *closure_each)
 {
@@ -93,11 +127,453 @@ extern "C" void GLSLangIterUniformNameMapping(const ShHandle handle, StrPairFunc
     }
 }
 
+// A flattened view of sh::ShaderVariable. Strings and array sizes point into
+// the compiler's own data and are only valid for the duration of the callback.
+struct GLSLangShaderVariable
+{
+    unsigned int type;
+    unsigned int precision;
+    const char *name;
+    size_t nameLength;
+    const char *mappedName;
+    size_t mappedNameLength;
+    const char *structOrBlockName;
+    size_t structOrBlockNameLength;
+    const char *mappedStructOrBlockName;
+    size_t mappedStructOrBlockNameLength;
+    const unsigned int *arraySizes;
+    size_t arraySizesLength;
+    int staticUse;
+    int active;
+    int isRowMajorLayout;
+    int location;
+    int binding;
+    unsigned int imageUnitFormat;
+    int offset;
+    int rasterOrdered;
+    int readonly;
+    int writeonly;
+    int isFragmentInOut;
+    int index;
+    int yuv;
+    int interpolation;
+    int isInvariant;
+    int isShaderIOBlock;
+    int isPatch;
+    size_t fieldsLength;
+};
+
+using ShaderVariableFunction = void (*)(void *, const GLSLangShaderVariable *);
+
+// Reports a variable and then, recursively, each of its struct fields, so
+// that the callee can rebuild the tree from fieldsLength.
+static void VisitShaderVariable(const sh::ShaderVariable &variable,
+                                ShaderVariableFunction each,
+                                void *closure_each)
+{
+    GLSLangShaderVariable flat;
+    flat.type                          = variable.type;
+    flat.precision                     = variable.precision;
+    flat.name                          = variable.name.data();
+    flat.nameLength                    = variable.name.length();
+    flat.mappedName                    = variable.mappedName.data();
+    flat.mappedNameLength              = variable.mappedName.length();
+    flat.structOrBlockName             = variable.structOrBlockName.data();
+    flat.structOrBlockNameLength       = variable.structOrBlockName.length();
+    flat.mappedStructOrBlockName       = variable.mappedStructOrBlockName.data();
+    flat.mappedStructOrBlockNameLength = variable.mappedStructOrBlockName.length();
+    flat.arraySizes                    = variable.arraySizes.data();
+    flat.arraySizesLength              = variable.arraySizes.size();
+    flat.staticUse                     = variable.staticUse;
+    flat.active                        = variable.active;
+    flat.isRowMajorLayout              = variable.isRowMajorLayout;
+    flat.location                      = variable.location;
+    flat.binding                       = variable.binding;
+    flat.imageUnitFormat               = variable.imageUnitFormat;
+    flat.offset                        = variable.offset;
+    flat.rasterOrdered                 = variable.rasterOrdered;
+    flat.readonly                      = variable.readonly;
+    flat.writeonly                     = variable.writeonly;
+    flat.isFragmentInOut               = variable.isFragmentInOut;
+    flat.index                         = variable.index;
+    flat.yuv                           = variable.yuv;
+    flat.interpolation                 = variable.interpolation;
+    flat.isInvariant                   = variable.isInvariant;
+    flat.isShaderIOBlock               = variable.isShaderIOBlock;
+    flat.isPatch                       = variable.isPatch;
+    flat.fieldsLength                  = variable.fields.size();
+    each(closure_each, &flat);
+
+    for (const auto &field : variable.fields)
+    {
+        VisitShaderVariable(field, each, closure_each);
+    }
+}
+
+static void VisitShaderVariables(const std::vector<sh::ShaderVariable> *variables,
+                                 ShaderVariableFunction each,
+                                 void *closure_each)
+{
+    if (!variables)
+        return;
+
+    for (const auto &variable : *variables)
+    {
+        VisitShaderVariable(variable, each, closure_each);
+    }
+}
+
+extern "C" void GLSLangIterUniforms(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetUniforms(handle), each, closure_each);
+}
+
+extern "C" void GLSLangIterAttributes(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetAttributes(handle), each, closure_each);
+}
+
+extern "C" void GLSLangIterVaryings(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetVaryings(handle), each, closure_each);
+}
+
+extern "C" void GLSLangIterInputVaryings(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetInputVaryings(handle), each, closure_each);
+}
+
+extern "C" void GLSLangIterOutputVaryings(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetOutputVaryings(handle), each, closure_each);
+}
+
+extern "C" void GLSLangIterOutputVariables(const ShHandle handle, ShaderVariableFunction each, void *closure_each)
+{
+    VisitShaderVariables(sh::GetOutputVariables(handle), each, closure_each);
+}
+
+// A flattened view of sh::InterfaceBlock, with the same lifetime rules as
+// GLSLangShaderVariable.
+struct GLSLangInterfaceBlock
+{
+    const char *name;
+    size_t nameLength;
+    const char *mappedName;
+    size_t mappedNameLength;
+    const char *instanceName;
+    size_t instanceNameLength;
+    unsigned int arraySize;
+    int layout;
+    int isRowMajorLayout;
+    int binding;
+    int staticUse;
+    int active;
+    int blockType;
+};
+
+using InterfaceBlockFunction = void (*)(void *, const GLSLangInterfaceBlock *);
+
+// Reports each block followed by the variable trees of its fields.
+static void VisitInterfaceBlocks(const std::vector<sh::InterfaceBlock> *blocks,
+                                 InterfaceBlockFunction eachBlock,
+                                 ShaderVariableFunction eachField,
+                                 void *closure_each)
+{
+    if (!blocks)
+        return;
+
+    for (const auto &block : *blocks)
+    {
+        GLSLangInterfaceBlock flat;
+        flat.name               = block.name.data();
+        flat.nameLength         = block.name.length();
+        flat.mappedName         = block.mappedName.data();
+        flat.mappedNameLength   = block.mappedName.length();
+        flat.instanceName       = block.instanceName.data();
+        flat.instanceNameLength = block.instanceName.length();
+        flat.arraySize          = block.arraySize;
+        flat.layout             = block.layout;
+        flat.isRowMajorLayout   = block.isRowMajorLayout;
+        flat.binding            = block.binding;
+        flat.staticUse          = block.staticUse;
+        flat.active             = block.active;
+        flat.blockType          = static_cast<int>(block.blockType);
+        eachBlock(closure_each, &flat);
+
+        VisitShaderVariables(&block.fields, eachField, closure_each);
+    }
+}
+
+extern "C" void GLSLangIterInterfaceBlocks(const ShHandle handle,
+                                           InterfaceBlockFunction eachBlock,
+                                           ShaderVariableFunction eachField,
+                                           void *closure_each)
+{
+    VisitInterfaceBlocks(sh::GetInterfaceBlocks(handle), eachBlock, eachField, closure_each);
+}
+
+extern "C" void GLSLangIterUniformBlocks(const ShHandle handle,
+                                         InterfaceBlockFunction eachBlock,
+                                         ShaderVariableFunction eachField,
+                                         void *closure_each)
+{
+    VisitInterfaceBlocks(sh::GetUniformBlocks(handle), eachBlock, eachField, closure_each);
+}
+
+extern "C" void GLSLangIterShaderStorageBlocks(const ShHandle handle,
+                                               InterfaceBlockFunction eachBlock,
+                                               ShaderVariableFunction eachField,
+                                               void *closure_each)
+{
+    VisitInterfaceBlocks(sh::GetShaderStorageBlocks(handle), eachBlock, eachField, closure_each);
+}
+
+// Writes the local work group size of a compute shader to sizeOut, which must
+// have room for three values. Undeclared dimensions are written as -1.
+extern "C" void GLSLangGetComputeShaderLocalGroupSize(const ShHandle handle, int *sizeOut)
+{
+    const sh::WorkGroupSize localSize = sh::GetComputeShaderLocalGroupSize(handle);
+    for (size_t i = 0; i < 3; i++)
+    {
+        sizeOut[i] = localSize[i];
+    }
+}
+
+extern "C" unsigned int GLSLangGetShaderSharedMemorySize(const ShHandle handle)
+{
+    return sh::GetShaderSharedMemorySize(handle);
+}
+
+// The primitive and tessellation queries below write their GL enum to
+// typeOut and return 1 if the shader declared the corresponding layout
+// qualifier, and return 0 otherwise.
+extern "C" int GLSLangGetGeometryShaderInputPrimitiveType(const ShHandle handle,
+                                                          unsigned int *typeOut)
+{
+    if (!sh::HasValidGeometryShaderInputPrimitiveType(handle))
+        return 0;
+
+    *typeOut = sh::GetGeometryShaderInputPrimitiveType(handle);
+    return 1;
+}
+
+extern "C" int GLSLangGetGeometryShaderOutputPrimitiveType(const ShHandle handle,
+                                                           unsigned int *typeOut)
+{
+    if (!sh::HasValidGeometryShaderOutputPrimitiveType(handle))
+        return 0;
+
+    *typeOut = sh::GetGeometryShaderOutputPrimitiveType(handle);
+    return 1;
+}
+
+extern "C" int GLSLangGetGeometryShaderInvocations(const ShHandle handle)
+{
+    return sh::GetGeometryShaderInvocations(handle);
+}
+
+// Returns -1 if the shader didn't declare max_vertices.
+extern "C" int GLSLangGetGeometryShaderMaxVertices(const ShHandle handle)
+{
+    if (!sh::HasValidGeometryShaderMaxVertices(handle))
+        return -1;
+
+    return sh::GetGeometryShaderMaxVertices(handle);
+}
+
+extern "C" int GLSLangGetTessControlShaderVertices(const ShHandle handle)
+{
+    return sh::GetTessControlShaderVertices(handle);
+}
+
+extern "C" int GLSLangGetTessGenMode(const ShHandle handle, unsigned int *typeOut)
+{
+    if (!sh::HasValidTessGenMode(handle))
+        return 0;
+
+    *typeOut = sh::GetTessGenMode(handle);
+    return 1;
+}
+
+extern "C" int GLSLangGetTessGenSpacing(const ShHandle handle, unsigned int *typeOut)
+{
+    if (!sh::HasValidTessGenSpacing(handle))
+        return 0;
+
+    *typeOut = sh::GetTessGenSpacing(handle);
+    return 1;
+}
+
+extern "C" int GLSLangGetTessGenVertexOrder(const ShHandle handle, unsigned int *typeOut)
+{
+    if (!sh::HasValidTessGenVertexOrder(handle))
+        return 0;
+
+    *typeOut = sh::GetTessGenVertexOrder(handle);
+    return 1;
+}
+
+extern "C" int GLSLangHasTessGenPointMode(const ShHandle handle)
+{
+    if (sh::HasValidTessGenPointMode(handle))
+        return 1;
+
+    return 0;
+}
+
+extern "C" void GLSLangIterNameHashingMap(const ShHandle handle, StrPairFunction each, void *closure_each)
+{
+    const std::map<std::string, std::string> *names = sh::GetNameHashingMap(handle);
+    if (!names)
+        return;
+
+    for (const auto &entry : *names)
+    {
+        each(
+            closure_each,
+            entry.first.data(), entry.first.length(),
+            entry.second.data(), entry.second.length());
+    }
+}
+
+// Exposes the protected TCompiler::getExtensionBehavior, since ShaderLang.h
+// has no accessor for it. The class is only used to take a pointer to the
+// member and is never instantiated. This relies on the TCompiler of the ANGLE
+// revision named in UPSTREAM (chromium/5359), and has to be checked whenever
+// ANGLE is updated.
+class ExtensionBehaviorAccess : public sh::TCompiler
+{
+  public:
+    using sh::TCompiler::getExtensionBehavior;
+};
+
+static const sh::TExtensionBehavior &GetExtensionBehavior(const sh::TCompiler *compiler)
+{
+    const sh::TExtensionBehavior &(sh::TCompiler::*getExtensionBehavior)() const =
+        &ExtensionBehaviorAccess::getExtensionBehavior;
+    return (compiler->*getExtensionBehavior)();
+}
+
+// Reports the behavior of each extension supported by the resources, as
+// left by the #extension directives of the last compilation. The behavior is
+// a sh::TBehavior.
+extern "C" void GLSLangIterExtensionBehavior(const ShHandle handle, StrUintFunction each, void *closure_each)
+{
+    sh::TShHandleBase *base = static_cast<sh::TShHandleBase *>(handle);
+    sh::TCompiler *compiler = base ? base->getAsCompiler() : nullptr;
+    if (!compiler)
+        return;
+
+    for (const auto &entry : GetExtensionBehavior(compiler))
+    {
+        const char *name = sh::GetExtensionNameString(entry.first);
+        each(closure_each, name, strlen(name), entry.second);
+    }
+}
+
+using ExtensionDirectiveFunction =
+    void (*)(void *, int, int, const char *, size_t, const char *, size_t, int);
+
+// The directive handler of the compiler, reporting each #extension directive
+// along with whether it was applied. TDirectiveHandler::handleExtension only
+// reports an error or a warning when it leaves the extension behavior alone,
+// so the directive was applied if it reported neither.
+class RecordingDirectiveHandler : public sh::TDirectiveHandler
+{
+  public:
+    RecordingDirectiveHandler(sh::TExtensionBehavior &extensionBehavior,
+                              sh::TDiagnostics &diagnostics,
+                              int &shaderVersion,
+                              sh::GLenum shaderType,
+                              ExtensionDirectiveFunction each,
+                              void *closure)
+        : sh::TDirectiveHandler(extensionBehavior, diagnostics, shaderVersion, shaderType),
+          mDiagnostics(diagnostics),
+          mEach(each),
+          mClosure(closure)
+    {}
+
+    void handleExtension(const angle::pp::SourceLocation &loc,
+                         const std::string &name,
+                         const std::string &behavior) override
+    {
+        int diagnostics = mDiagnostics.numErrors() + mDiagnostics.numWarnings();
+        sh::TDirectiveHandler::handleExtension(loc, name, behavior);
+        bool honoured = mDiagnostics.numErrors() + mDiagnostics.numWarnings() == diagnostics;
+        mEach(mClosure, loc.file, loc.line, name.data(), name.length(), behavior.data(),
+              behavior.length(), honoured);
+    }
+
+  private:
+    sh::TDiagnostics &mDiagnostics;
+    ExtensionDirectiveFunction mEach;
+    void *mClosure;
+};
+
+// Reports the #extension directives of shader strings compiled with the
+// handle, in order. The compiler keeps its parse context, and so its
+// directive handler, local to a compilation, so this is a second
+// preprocessing pass after the compilation rather than a record of it. The
+// pass is set up the way glslang_scan in glslang.l sets the preprocessor up,
+// from the spec and resources of the compiler, with the extension behavior
+// reset the way TCompiler::compileTreeImpl resets it before parsing.
+extern "C" void GLSLangGetExtensionDirectives(const ShHandle handle,
+                                              const char *const strings[],
+                                              size_t count,
+                                              ExtensionDirectiveFunction each,
+                                              void *closure)
+{
+    sh::TShHandleBase *base = static_cast<sh::TShHandleBase *>(handle);
+    sh::TCompiler *compiler = base ? base->getAsCompiler() : nullptr;
+    if (!compiler || count == 0)
+        return;
+
+    ShShaderSpec spec = compiler->getShaderSpec();
+    const ShBuiltInResources &resources = compiler->getResources();
+    // The extensions left by the compilation, which already lack those that
+    // compileTreeImpl removes for its compile options. The compile options
+    // themselves are private, but ARB_texture_rectangle is only missing from a
+    // compilation of resources supporting it when they disabled it.
+    sh::TExtensionBehavior extensionBehavior = GetExtensionBehavior(compiler);
+    ShCompileOptions compileOptions;
+    compileOptions.disableARBTextureRectangle =
+        extensionBehavior.count(sh::TExtension::ARB_texture_rectangle) == 0;
+    sh::ResetExtensionBehavior(resources, extensionBehavior, compileOptions);
+
+    sh::TInfoSinkBase infoSink;
+    sh::TDiagnostics diagnostics(infoSink);
+    int shaderVersion = 100;
+    RecordingDirectiveHandler directiveHandler(extensionBehavior, diagnostics, shaderVersion,
+                                               compiler->getShaderType(), each, closure);
+    angle::pp::Preprocessor preprocessor(&diagnostics, &directiveHandler,
+                                         angle::pp::PreprocessorSettings(spec));
+    if (!preprocessor.init(count, strings, nullptr))
+        return;
+
+    for (const auto &entry : extensionBehavior)
+    {
+        if (sh::IsWebGLBasedSpec(spec) && entry.first == sh::TExtension::OVR_multiview)
+            continue;
+        preprocessor.predefineMacro(sh::GetExtensionNameString(entry.first), 1);
+    }
+    if (resources.FragmentPrecisionHigh == 1)
+        preprocessor.predefineMacro("GL_FRAGMENT_PRECISION_HIGH", 1);
+    preprocessor.setMaxTokenSize(sh::GetGlobalMaxTokenSize(spec));
+
+    angle::pp::Token token;
+    do
+    {
+        preprocessor.lex(&token);
+    } while (token.type != angle::pp::Token::LAST);
+}
+
 // Returns the number of vectors that the shader's active varyings fit
 // in to without additional packing. Can be used to test whether a
 // shader will compile on drivers that do not perform spec-compliant
 // packing. This contrasts with sh::CheckVariablesWithinPackingLimits
-// which does pack the varyings in accordance with the spec.
+// which does pack the varyings in accordance with the spec, and which is
+// available from Rust as pack_varyings.
 extern "C" int GLSLangGetNumUnpackedVaryingVectors(const ShHandle handle)
 {
     int total_rows = 0;
@@ -116,3 +592,522 @@ extern "C" int GLSLangGetNumUnpackedVaryingVectors(const ShHandle handle)
 
     return total_rows;
 }
+
+// Reports how a non-struct type is packed by sh::CheckVariablesWithinPackingLimits:
+// the number of columns and rows it takes, and its position in the packing
+// order of the GLSL ES 1.00 spec, section A.7.
+extern "C" void GLSLangGetTypePackingInfo(unsigned int type,
+                                          int *componentsPerRowOut,
+                                          int *rowsOut,
+                                          int *sortOrderOut)
+{
+    *componentsPerRowOut = sh::GetTypePackingComponentsPerRow(type);
+    *rowsOut             = sh::GetTypePackingRows(type);
+    *sortOrderOut        = gl::VariableSortOrder(type);
+}
+
+// The HLSL queries below assume an HLSL translator and dereference it
+// unconditionally, so they are only forwarded for HLSL output.
+static bool IsHLSLOutput(const ShHandle handle)
+{
+    switch (sh::GetShaderOutputType(handle))
+    {
+        case SH_HLSL_3_0_OUTPUT:
+        case SH_HLSL_4_1_OUTPUT:
+        case SH_HLSL_4_0_FL9_3_OUTPUT:
+            return true;
+        default:
+            return false;
+    }
+}
+
+extern "C" void GLSLangIterUniformRegisterMap(const ShHandle handle, StrUintFunction each, void *closure_each)
+{
+    if (!IsHLSLOutput(handle))
+        return;
+
+    const std::map<std::string, unsigned int> *registers = sh::GetUniformRegisterMap(handle);
+    if (!registers)
+        return;
+
+    for (const auto &entry : *registers)
+    {
+        each(closure_each, entry.first.data(), entry.first.length(), entry.second);
+    }
+}
+
+extern "C" int GLSLangGetUniformBlockRegister(const ShHandle handle,
+                                              const char *name,
+                                              size_t nameLength,
+                                              unsigned int *indexOut)
+{
+    if (!IsHLSLOutput(handle))
+        return 0;
+
+    if (sh::GetUniformBlockRegister(handle, std::string(name, nameLength), indexOut))
+        return 1;
+
+    return 0;
+}
+
+extern "C" int GLSLangGetShaderStorageBlockRegister(const ShHandle handle,
+                                                    const char *name,
+                                                    size_t nameLength,
+                                                    unsigned int *indexOut)
+{
+    if (!IsHLSLOutput(handle))
+        return 0;
+
+    if (sh::GetShaderStorageBlockRegister(handle, std::string(name, nameLength), indexOut))
+        return 1;
+
+    return 0;
+}
+
+extern "C" int GLSLangShouldUniformBlockUseStructuredBuffer(const ShHandle handle,
+                                                            const char *name,
+                                                            size_t nameLength)
+{
+    if (!IsHLSLOutput(handle))
+        return 0;
+
+    if (sh::ShouldUniformBlockUseStructuredBuffer(handle, std::string(name, nameLength)))
+        return 1;
+
+    return 0;
+}
+
+extern "C" void GLSLangIterSlowCompilingUniformBlocks(const ShHandle handle, StrFunction each, void *closure_each)
+{
+    if (!IsHLSLOutput(handle))
+        return;
+
+    const std::set<std::string> *blocks = sh::GetSlowCompilingUniformBlockSet(handle);
+    if (!blocks)
+        return;
+
+    for (const auto &block : *blocks)
+    {
+        each(closure_each, block.data(), block.length());
+    }
+}
+
+// Returns the first register reserved for image2D variables, or -1 if the
+// shader wasn't translated to HLSL.
+extern "C" int GLSLangGetImage2DRegisterIndex(const ShHandle handle)
+{
+    if (!IsHLSLOutput(handle))
+        return -1;
+
+    return static_cast<int>(sh::GetImage2DRegisterIndex(handle));
+}
+
+// Same as GLSLangGetImage2DRegisterIndex, for readonly image2D variables.
+extern "C" int GLSLangGetReadonlyImage2DRegisterIndex(const ShHandle handle)
+{
+    if (!IsHLSLOutput(handle))
+        return -1;
+
+    return static_cast<int>(sh::GetReadonlyImage2DRegisterIndex(handle));
+}
+
+// Rebuilds a variable tree flattened the same way VisitShaderVariable does.
+static sh::ShaderVariable UnflattenShaderVariable(const GLSLangShaderVariable *variables,
+                                                  size_t *index)
+{
+    const GLSLangShaderVariable &flat = variables[(*index)++];
+
+    sh::ShaderVariable variable(flat.type);
+    variable.precision = flat.precision;
+    variable.name.assign(flat.name, flat.nameLength);
+    variable.mappedName.assign(flat.mappedName, flat.mappedNameLength);
+    variable.structOrBlockName.assign(flat.structOrBlockName, flat.structOrBlockNameLength);
+    variable.mappedStructOrBlockName.assign(flat.mappedStructOrBlockName,
+                                            flat.mappedStructOrBlockNameLength);
+    variable.arraySizes.assign(flat.arraySizes, flat.arraySizes + flat.arraySizesLength);
+    variable.staticUse        = flat.staticUse != 0;
+    variable.active           = flat.active != 0;
+    variable.isRowMajorLayout = flat.isRowMajorLayout != 0;
+    variable.location         = flat.location;
+    variable.binding          = flat.binding;
+    variable.imageUnitFormat  = flat.imageUnitFormat;
+    variable.offset           = flat.offset;
+    variable.rasterOrdered    = flat.rasterOrdered != 0;
+    variable.readonly         = flat.readonly != 0;
+    variable.writeonly        = flat.writeonly != 0;
+    variable.isFragmentInOut  = flat.isFragmentInOut != 0;
+    variable.index            = flat.index;
+    variable.yuv              = flat.yuv != 0;
+    variable.interpolation    = static_cast<sh::InterpolationType>(flat.interpolation);
+    variable.isInvariant      = flat.isInvariant != 0;
+    variable.isShaderIOBlock  = flat.isShaderIOBlock != 0;
+    variable.isPatch          = flat.isPatch != 0;
+
+    for (size_t i = 0; i < flat.fieldsLength; i++)
+    {
+        variable.fields.push_back(UnflattenShaderVariable(variables, index));
+    }
+    return variable;
+}
+
+// Checks whether varyings fit in maxVectors rows with
+// sh::CheckVariablesWithinPackingLimits. The variables are flattened as for
+// UnflattenShaderVariable.
+extern "C" int GLSLangCheckVariablesWithinPackingLimits(int maxVectors,
+                                                        const GLSLangShaderVariable *variables,
+                                                        size_t variablesLength)
+{
+    std::vector<sh::ShaderVariable> unflattened;
+    size_t index = 0;
+    while (index < variablesLength)
+    {
+        unflattened.push_back(UnflattenShaderVariable(variables, &index));
+    }
+
+    if (sh::CheckVariablesWithinPackingLimits(maxVectors, unflattened))
+        return 1;
+
+    return 0;
+}
+
+using BlockMemberFunction = void (*)(void *, const char *, size_t, int, int, int, int, int);
+
+// Lays out the fields of an interface block with ANGLE's own std140 or std430
+// encoder, reporting every non-opaque leaf member by its GL API name and
+// returning the size of the block in bytes.
+extern "C" size_t GLSLangGetInterfaceBlockLayout(const GLSLangShaderVariable *fields,
+                                                 size_t fieldsLength,
+                                                 const char *prefix,
+                                                 size_t prefixLength,
+                                                 int std430,
+                                                 BlockMemberFunction each,
+                                                 void *closure_each)
+{
+    std::vector<sh::ShaderVariable> variables;
+    size_t index = 0;
+    while (index < fieldsLength)
+    {
+        variables.push_back(UnflattenShaderVariable(fields, &index));
+    }
+
+    sh::Std140BlockEncoder std140Encoder;
+    sh::Std430BlockEncoder std430Encoder;
+    sh::BlockLayoutEncoder *encoder = std430 ? &std430Encoder : &std140Encoder;
+
+    sh::BlockLayoutMap members;
+    sh::GetInterfaceBlockInfo(variables, std::string(prefix, prefixLength), encoder, &members);
+
+    for (const auto &member : members)
+    {
+        const sh::BlockMemberInfo &info = member.second;
+        each(closure_each, member.first.data(), member.first.length(), info.offset,
+             info.arrayStride, info.matrixStride, info.isRowMajorMatrix,
+             info.topLevelArrayStride);
+    }
+
+    return encoder->getCurrentOffset();
+}
+
+static sh::InterfaceBlock UnflattenInterfaceBlock(const GLSLangInterfaceBlock &flat,
+                                                  const GLSLangShaderVariable *fields,
+                                                  size_t fieldsLength)
+{
+    sh::InterfaceBlock block;
+    block.name.assign(flat.name, flat.nameLength);
+    block.mappedName.assign(flat.mappedName, flat.mappedNameLength);
+    block.instanceName.assign(flat.instanceName, flat.instanceNameLength);
+    block.arraySize        = flat.arraySize;
+    block.layout           = static_cast<sh::BlockLayoutType>(flat.layout);
+    block.isRowMajorLayout = flat.isRowMajorLayout != 0;
+    block.binding          = flat.binding;
+    block.staticUse        = flat.staticUse != 0;
+    block.active           = flat.active != 0;
+    block.blockType        = static_cast<sh::BlockType>(flat.blockType);
+
+    size_t index = 0;
+    while (index < fieldsLength)
+    {
+        block.fields.push_back(UnflattenShaderVariable(fields, &index));
+    }
+    return block;
+}
+
+// The link-time comparisons below take variables flattened the same way
+// VisitShaderVariable reports them.
+extern "C" int GLSLangIsSameUniformAtLinkTime(const GLSLangShaderVariable *a,
+                                              const GLSLangShaderVariable *b)
+{
+    size_t aIndex = 0, bIndex = 0;
+    if (UnflattenShaderVariable(a, &aIndex).isSameUniformAtLinkTime(UnflattenShaderVariable(b, &bIndex)))
+        return 1;
+
+    return 0;
+}
+
+extern "C" int GLSLangIsSameVaryingAtLinkTime(const GLSLangShaderVariable *a,
+                                              const GLSLangShaderVariable *b,
+                                              int shaderVersion)
+{
+    size_t aIndex = 0, bIndex = 0;
+    if (UnflattenShaderVariable(a, &aIndex)
+            .isSameVaryingAtLinkTime(UnflattenShaderVariable(b, &bIndex), shaderVersion))
+        return 1;
+
+    return 0;
+}
+
+extern "C" int GLSLangIsSameInterfaceBlockAtLinkTime(const GLSLangInterfaceBlock *a,
+                                                     const GLSLangShaderVariable *aFields,
+                                                     size_t aFieldsLength,
+                                                     const GLSLangInterfaceBlock *b,
+                                                     const GLSLangShaderVariable *bFields,
+                                                     size_t bFieldsLength)
+{
+    if (UnflattenInterfaceBlock(*a, aFields, aFieldsLength)
+            .isSameInterfaceBlockAtLinkTime(UnflattenInterfaceBlock(*b, bFields, bFieldsLength)))
+        return 1;
+
+    return 0;
+}
+
+using PreprocessorTokenFunction = void (*)(void *, int, const char *, size_t, int, int, unsigned int);
+using PreprocessorDirectiveFunction =
+    void (*)(void *, int, int, int, const char *, size_t, const char *, size_t, int);
+using PreprocessorDiagnosticFunction =
+    void (*)(void *, int, int, int, const char *, size_t, const char *, size_t);
+using PreprocessorMacroFunction =
+    void (*)(void *, const char *, size_t, int, int, const char *, size_t, const char *, size_t);
+
+// The kinds of directives reported by GLSLangPreprocess.
+enum GLSLangPreprocessorDirective
+{
+    GLSLANG_DIRECTIVE_VERSION,
+    GLSLANG_DIRECTIVE_EXTENSION,
+    GLSLANG_DIRECTIVE_PRAGMA,
+    GLSLANG_DIRECTIVE_ERROR,
+};
+
+class PreprocessorCallbacks : public angle::pp::Diagnostics, public angle::pp::DirectiveHandler
+{
+  public:
+    PreprocessorCallbacks(PreprocessorDirectiveFunction eachDirective,
+                          PreprocessorDiagnosticFunction eachDiagnostic,
+                          void *closure)
+        : mEachDirective(eachDirective), mEachDiagnostic(eachDiagnostic), mClosure(closure), mErrors(0)
+    {}
+
+    int errors() const { return mErrors; }
+
+    void handleError(const angle::pp::SourceLocation &loc, const std::string &msg) override
+    {
+        directive(GLSLANG_DIRECTIVE_ERROR, loc, std::string(), msg, 0);
+    }
+
+    void handlePragma(const angle::pp::SourceLocation &loc,
+                      const std::string &name,
+                      const std::string &value,
+                      bool stdgl) override
+    {
+        directive(GLSLANG_DIRECTIVE_PRAGMA, loc, name, value, stdgl);
+    }
+
+    void handleExtension(const angle::pp::SourceLocation &loc,
+                         const std::string &name,
+                         const std::string &behavior) override
+    {
+        directive(GLSLANG_DIRECTIVE_EXTENSION, loc, name, behavior, 0);
+    }
+
+    void handleVersion(const angle::pp::SourceLocation &loc, int version, ShShaderSpec) override
+    {
+        directive(GLSLANG_DIRECTIVE_VERSION, loc, std::string(), std::string(), version);
+    }
+
+  protected:
+    void print(ID id, const angle::pp::SourceLocation &loc, const std::string &text) override
+    {
+        bool error = isError(id);
+        if (error)
+            ++mErrors;
+
+        std::string msg = message(id);
+        mEachDiagnostic(mClosure, error, loc.file, loc.line, msg.data(), msg.length(), text.data(),
+                        text.length());
+    }
+
+  private:
+    void directive(int kind,
+                   const angle::pp::SourceLocation &loc,
+                   const std::string &name,
+                   const std::string &value,
+                   int number)
+    {
+        mEachDirective(mClosure, kind, loc.file, loc.line, name.data(), name.length(),
+                       value.data(), value.length(), number);
+    }
+
+    PreprocessorDirectiveFunction mEachDirective;
+    PreprocessorDiagnosticFunction mEachDiagnostic;
+    void *mClosure;
+    int mErrors;
+};
+
+// A macro predefined by GLSLangPreprocess. The parameters of function-like
+// macros are separated by commas.
+struct GLSLangMacroDefinition
+{
+    const char *name;
+    size_t nameLength;
+    int function;
+    const char *parameters;
+    size_t parametersLength;
+    const char *replacement;
+    size_t replacementLength;
+};
+
+// Builds a predefined macro the way DirectiveParser::parseDefine does, from a
+// definition whose name and parameters were already checked.
+static std::shared_ptr<angle::pp::Macro> PredefinedMacro(const GLSLangMacroDefinition &definition,
+                                                         angle::pp::Diagnostics *diagnostics,
+                                                         size_t maxTokenSize)
+{
+    // Predefined object-like macros are expected to be a single token, like
+    // __LINE__, so these are left as ordinary macros.
+    std::shared_ptr<angle::pp::Macro> macro = std::make_shared<angle::pp::Macro>();
+    macro->type = definition.function ? angle::pp::Macro::kTypeFunc : angle::pp::Macro::kTypeObj;
+    macro->name.assign(definition.name, definition.nameLength);
+
+    std::string parameters(definition.parameters, definition.parametersLength);
+    size_t start = 0;
+    while (start < parameters.length())
+    {
+        size_t end = parameters.find(',', start);
+        if (end == std::string::npos)
+            end = parameters.length();
+        macro->parameters.push_back(parameters.substr(start, end - start));
+        start = end + 1;
+    }
+
+    // The tokenizer doesn't handle empty input.
+    if (definition.replacementLength == 0)
+        return macro;
+
+    angle::pp::Tokenizer tokenizer(diagnostics);
+    const char *strings[] = {definition.replacement};
+    const int lengths[]   = {static_cast<int>(definition.replacementLength)};
+    if (tokenizer.init(1, strings, lengths))
+    {
+        tokenizer.setMaxTokenSize(maxTokenSize);
+        angle::pp::Token token;
+        tokenizer.lex(&token);
+        while (token.type != '\n' && token.type != angle::pp::Token::LAST)
+        {
+            token.location = angle::pp::SourceLocation();
+            macro->replacements.push_back(token);
+            tokenizer.lex(&token);
+        }
+    }
+    if (!macro->replacements.empty())
+        macro->replacements.front().setHasLeadingSpace(false);
+
+    return macro;
+}
+
+// Runs the preprocessor the way angle::pp::Preprocessor does, but keeps the
+// macro set around so that the macros defined by the shader can be reported
+// once every token has been lexed. Returns 1 if there were no errors.
+extern "C" int GLSLangPreprocess(unsigned int spec,
+                                 const char *const *strings,
+                                 const int *lengths,
+                                 size_t count,
+                                 const GLSLangMacroDefinition *macros,
+                                 size_t macroCount,
+                                 PreprocessorTokenFunction eachToken,
+                                 PreprocessorDirectiveFunction eachDirective,
+                                 PreprocessorDiagnosticFunction eachDiagnostic,
+                                 PreprocessorMacroFunction eachMacro,
+                                 void *closure)
+{
+    ShShaderSpec shaderSpec = static_cast<ShShaderSpec>(spec);
+    angle::pp::PreprocessorSettings settings(shaderSpec);
+    PreprocessorCallbacks callbacks(eachDirective, eachDiagnostic, closure);
+
+    angle::pp::MacroSet macroSet;
+    angle::pp::Tokenizer tokenizer(&callbacks);
+    angle::pp::DirectiveParser directiveParser(&tokenizer, &macroSet, &callbacks, &callbacks,
+                                               settings);
+    angle::pp::MacroExpander macroExpander(&directiveParser, &macroSet, &callbacks, settings,
+                                           false);
+
+    angle::pp::PredefineMacro(&macroSet, "__LINE__", 0);
+    angle::pp::PredefineMacro(&macroSet, "__FILE__", 0);
+    angle::pp::PredefineMacro(&macroSet, "__VERSION__", 100);
+    angle::pp::PredefineMacro(&macroSet, "GL_ES", 1);
+    // Kept alive so that a macro the shader redefines can't take the place
+    // of one of these.
+    angle::pp::MacroSet definedMacros;
+    for (size_t i = 0; i < macroCount; ++i)
+    {
+        std::shared_ptr<angle::pp::Macro> macro =
+            PredefinedMacro(macros[i], &callbacks, sh::GetGlobalMaxTokenSize(shaderSpec));
+        definedMacros[macro->name] = macro;
+        macroSet[macro->name]      = macro;
+    }
+
+    if (!tokenizer.init(count, strings, lengths))
+        return 0;
+    tokenizer.setMaxTokenSize(sh::GetGlobalMaxTokenSize(shaderSpec));
+
+    angle::pp::Token token;
+    do
+    {
+        macroExpander.lex(&token);
+        switch (token.type)
+        {
+            case angle::pp::Token::PP_NUMBER:
+                callbacks.report(angle::pp::Diagnostics::PP_INVALID_NUMBER, token.location,
+                                 token.text);
+                break;
+            case angle::pp::Token::PP_OTHER:
+                callbacks.report(angle::pp::Diagnostics::PP_INVALID_CHARACTER, token.location,
+                                 token.text);
+                break;
+            case angle::pp::Token::LAST:
+                break;
+            default:
+                eachToken(closure, token.type, token.text.data(), token.text.length(),
+                          token.location.file, token.location.line, token.flags);
+                break;
+        }
+    } while (token.type != angle::pp::Token::LAST);
+
+    for (const auto &entry : macroSet)
+    {
+        const angle::pp::Macro &macro = *entry.second;
+
+        std::string parameters;
+        for (const std::string &parameter : macro.parameters)
+        {
+            if (!parameters.empty())
+                parameters += ",";
+            parameters += parameter;
+        }
+
+        std::string replacement;
+        for (const angle::pp::Token &replacementToken : macro.replacements)
+        {
+            if (!replacement.empty() && replacementToken.hasLeadingSpace())
+                replacement += " ";
+            replacement += replacementToken.text;
+        }
+
+        auto defined    = definedMacros.find(entry.first);
+        bool predefined = macro.predefined ||
+                          (defined != definedMacros.end() && defined->second == entry.second);
+        eachMacro(closure, macro.name.data(), macro.name.length(), predefined,
+                  macro.type == angle::pp::Macro::kTypeFunc, parameters.data(), parameters.length(),
+                  replacement.data(), replacement.length());
+    }
+
+    return callbacks.errors() == 0;
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Finds the files named by `#include` directives.
+pub trait IncludeResolver {
+    /// Returns the name and contents of the file that `#include "path"` or
+    /// `#include <path>` refers to in the file named `includer`.
+    ///
+    /// The name identifies the file in diagnostics and when looking for
+    /// include cycles, so it should be the same however the file is reached.
+    fn resolve(&mut self, path: &str, includer: &str) -> Result<(String, String), String>;
+}
+
+/// Resolves includes from a map of names to contents, ignoring the file
+/// they are included from.
+impl IncludeResolver for HashMap<String, String> {
+    fn resolve(&mut self, path: &str, _includer: &str) -> Result<(String, String), String> {
+        match self.get(path) {
+            Some(source) => Ok((path.to_owned(), source.clone())),
+            None => Err("no such file".to_owned()),
+        }
+    }
+}
+
+/// Resolves includes relative to the directory of the including file, then
+/// in each of the search paths.
+#[derive(Clone, Debug, Default)]
+pub struct FileSystemResolver {
+    search_paths: Vec<PathBuf>,
+}
+
+impl FileSystemResolver {
+    pub fn new() -> Self {
+        FileSystemResolver::default()
+    }
+
+    pub fn search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+}
+
+impl IncludeResolver for FileSystemResolver {
+    fn resolve(&mut self, path: &str, includer: &str) -> Result<(String, String), String> {
+        let relative = Path::new(includer).parent().map(|dir| dir.join(path));
+        let candidates = relative
+            .into_iter()
+            .chain(self.search_paths.iter().map(|dir| dir.join(path)));
+        for candidate in candidates {
+            if let Ok(source) = fs::read_to_string(&candidate) {
+                let name = fs::canonicalize(&candidate).unwrap_or(candidate);
+                return Ok((name.display().to_string(), source));
+            }
+        }
+        Err("no such file".to_owned())
+    }
+}
+
+/// The ways in which expanding includes can fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IncludeError {
+    /// The resolver couldn't provide an included file.
+    Unresolved {
+        /// The path in the `#include` directive.
+        path: String,
+        /// The name of the file with the directive.
+        includer: String,
+        /// One-based line of the directive.
+        line: u32,
+        /// Why the resolver failed.
+        reason: String,
+    },
+    /// A file includes itself, directly or not, without an include guard or
+    /// `#pragma once`.
+    Cycle {
+        /// The names of the files in the cycle, starting and ending with the
+        /// same one.
+        files: Vec<String>,
+    },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IncludeError::Unresolved {
+                ref path,
+                ref includer,
+                line,
+                ref reason,
+            } => write!(
+                f,
+                "{}:{}: Couldn't include \"{}\": {}",
+                includer, line, path, reason
+            ),
+            IncludeError::Cycle { ref files } => {
+                write!(f, "Include cycle: {}", files.join(" -> "))
+            }
+        }
+    }
+}
+
+impl error::Error for IncludeError {}
+
+/// A shader with its includes spliced in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpandedSource {
+    /// The source, with `#line` directives numbering each part after the
+    /// file it came from.
+    pub source: String,
+    /// The names of the files, indexed by the source string numbers of the
+    /// `#line` directives. The root file is first.
+    pub names: Vec<String>,
+}
+
+/// Replaces the `#include` directives of `source`, the contents of the file
+/// `name`, with the files they name, recursively.
+///
+/// Files with `#pragma once`, or starting with an `#ifndef`/`#define` include
+/// guard, are only included once. Directives in comments are ignored.
+///
+/// Conditionals are left to the compiler, so includes are expanded even
+/// inside `#if` blocks. Since the block may be skipped, files expanded there
+/// only count as included, for `#pragma once` and include guards, by the
+/// files they include themselves, and the lines after the block are numbered
+/// again. When the resolver can't provide a file included there, the
+/// directive is replaced with an `#error`, which the compiler only reports if
+/// the block is active.
+pub fn expand_includes<R: IncludeResolver + ?Sized>(
+    resolver: &mut R,
+    name: &str,
+    source: &str,
+) -> Result<ExpandedSource, IncludeError> {
+    let mut expander = Expander {
+        resolver,
+        expanded: ExpandedSource {
+            source: String::with_capacity(source.len()),
+            names: vec![name.to_owned()],
+        },
+        stack: vec![name.to_owned()],
+        once: HashSet::new(),
+        guards: HashSet::new(),
+    };
+    expander.expand(0, source, false)?;
+    Ok(expander.expanded)
+}
+
+struct Expander<'a, R: ?Sized + 'a> {
+    resolver: &'a mut R,
+    expanded: ExpandedSource,
+    /// The names of the files being expanded, outermost first.
+    stack: Vec<String>,
+    /// Files with `#pragma once` that were already included.
+    once: HashSet<String>,
+    /// Include guard macros that are defined.
+    guards: HashSet<String>,
+}
+
+impl<'a, R: IncludeResolver + ?Sized> Expander<'a, R> {
+    /// Expands the file with source string number `index`, which is
+    /// `conditional` when it is included in a conditional block.
+    fn expand(
+        &mut self,
+        index: usize,
+        source: &str,
+        conditional: bool,
+    ) -> Result<(), IncludeError> {
+        // A conditional expansion only counts as included within itself, for
+        // the files it includes.
+        let guard = include_guard(source);
+        let mut scoped_guard = None;
+        if let Some(guard) = guard {
+            if self.guards.insert(guard.to_owned()) && conditional {
+                scoped_guard = Some(guard);
+            }
+        }
+        let mut scoped_once = None;
+
+        let mut in_comment = false;
+        // The number of conditional blocks the line is in.
+        let mut depth: u32 = 0;
+        // Whether the outermost block is the include guard, which is taken
+        // since the file wasn't skipped.
+        let mut in_guard = guard.is_some();
+        // Whether a file was included in the current conditional blocks. If
+        // they are skipped, so are the `#line` directives around it, but its
+        // lines are still counted.
+        let mut renumber = false;
+        for (i, line) in source.split('\n').enumerate() {
+            if i > 0 {
+                self.expanded.source.push('\n');
+            }
+            let code = strip_comments(line, &mut in_comment);
+            match directive(&code) {
+                Some(("pragma", "once")) => {
+                    let name = self.stack.last().unwrap().clone();
+                    if self.once.insert(name.clone()) && conditional {
+                        scoped_once = Some(name);
+                    }
+                }
+                Some(("include", argument)) => {
+                    let in_block = depth > in_guard as u32;
+                    self.include(index, i as u32 + 1, argument, conditional || in_block)?;
+                    renumber |= in_block;
+                }
+                Some(("if", _)) | Some(("ifdef", _)) | Some(("ifndef", _)) => {
+                    depth += 1;
+                    self.expanded.source.push_str(line);
+                }
+                Some((name @ "else", _)) | Some((name @ "elif", _)) | Some((name @ "endif", _)) => {
+                    if name == "endif" {
+                        depth = depth.saturating_sub(1);
+                        in_guard &= depth > 0;
+                    }
+                    self.expanded.source.push_str(line);
+                    // The following lines may be the first ones the compiler
+                    // doesn't skip.
+                    if renumber {
+                        self.expanded
+                            .source
+                            .push_str(&format!("\n#line {} {}", i + 2, index));
+                        renumber = depth > 0;
+                    }
+                }
+                _ => self.expanded.source.push_str(line),
+            }
+        }
+
+        if let Some(guard) = scoped_guard {
+            self.guards.remove(guard);
+        }
+        if let Some(name) = scoped_once {
+            self.once.remove(&name);
+        }
+        Ok(())
+    }
+
+    fn include(
+        &mut self,
+        index: usize,
+        line: u32,
+        argument: &str,
+        conditional: bool,
+    ) -> Result<(), IncludeError> {
+        let includer = self.stack.last().unwrap().clone();
+        let resolved = match include_path(argument) {
+            Some(path) => self
+                .resolver
+                .resolve(path, &includer)
+                .map_err(|reason| (path, reason)),
+            None => Err((argument, "expected \"path\" or <path>".to_owned())),
+        };
+        let (name, source) = match resolved {
+            Ok(resolved) => resolved,
+            Err((path, reason)) if conditional => {
+                // Left to the compiler, which skips inactive blocks.
+                self.expanded
+                    .source
+                    .push_str(&format!("#error cannot include {}: {}", path, reason));
+                return Ok(());
+            }
+            Err((path, reason)) => {
+                return Err(IncludeError::Unresolved {
+                    path: path.to_owned(),
+                    includer,
+                    line,
+                    reason,
+                })
+            }
+        };
+
+        let guarded = match include_guard(&source) {
+            Some(guard) => self.guards.contains(guard),
+            None => false,
+        };
+        if guarded || self.once.contains(&name) {
+            return Ok(());
+        }
+        if let Some(start) = self.stack.iter().position(|file| *file == name) {
+            let mut files = self.stack[start..].to_vec();
+            files.push(name);
+            return Err(IncludeError::Cycle { files });
+        }
+
+        let included = match self.expanded.names.iter().position(|file| *file == name) {
+            Some(included) => included,
+            None => {
+                self.expanded.names.push(name.clone());
+                self.expanded.names.len() - 1
+            }
+        };
+        // The directive replaces the `#include` line and numbers the next
+        // line, which is the first of the included file.
+        self.expanded
+            .source
+            .push_str(&format!("#line 1 {}\n", included));
+        self.stack.push(name);
+        self.expand(included, &source, conditional)?;
+        self.stack.pop();
+        self.expanded
+            .source
+            .push_str(&format!("\n#line {} {}", line + 1, index));
+        Ok(())
+    }
+}
+
+/// Splits a preprocessor directive into its name and the rest of the line.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start().strip_prefix('#')?.trim_start();
+    let end = line
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(line.len());
+    Some((&line[..end], line[end..].trim()))
+}
+
+/// Returns `line` without its comments, given whether it starts inside a
+/// block comment, which is updated to whether it ends inside one.
+fn strip_comments(line: &str, in_comment: &mut bool) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut rest = line;
+    loop {
+        if *in_comment {
+            match rest.find("*/") {
+                Some(end) => {
+                    // Comments separate tokens like whitespace.
+                    code.push(' ');
+                    rest = &rest[end + 2..];
+                    *in_comment = false;
+                }
+                None => return code,
+            }
+        } else {
+            match (rest.find("/*"), rest.find("//")) {
+                (Some(start), Some(line_comment)) if line_comment < start => {
+                    code.push_str(&rest[..line_comment]);
+                    return code;
+                }
+                (Some(start), _) => {
+                    code.push_str(&rest[..start]);
+                    rest = &rest[start + 2..];
+                    *in_comment = true;
+                }
+                (None, Some(line_comment)) => {
+                    code.push_str(&rest[..line_comment]);
+                    return code;
+                }
+                (None, None) => {
+                    code.push_str(rest);
+                    return code;
+                }
+            }
+        }
+    }
+}
+
+fn include_path(argument: &str) -> Option<&str> {
+    let argument = argument
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| {
+            argument
+                .strip_prefix('<')
+                .and_then(|rest| rest.strip_suffix('>'))
+        })?;
+    if argument.is_empty() {
+        None
+    } else {
+        Some(argument)
+    }
+}
+
+/// Returns the macro of an `#ifndef X` / `#define X` include guard that
+/// starts `source`, ignoring blank lines and line comments.
+fn include_guard(source: &str) -> Option<&str> {
+    let mut directives = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(directive);
+    match (directives.next()?, directives.next()?) {
+        (Some(("ifndef", guard)), Some(("define", defined))) if guard == defined => Some(guard),
+        _ => None,
+    }
+}
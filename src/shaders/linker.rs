@@ -0,0 +1,264 @@
+use super::ffi::{
+    GLSLangIsSameInterfaceBlockAtLinkTime, GLSLangIsSameUniformAtLinkTime,
+    GLSLangIsSameVaryingAtLinkTime,
+};
+use super::variables::{flatten, flatten_block};
+use super::{InterfaceBlock, ShaderType, ShaderValidator, ShaderVariable};
+
+use std::error;
+use std::fmt;
+use std::os::raw::c_int;
+use std::slice;
+
+/// A reason why two shaders can't be linked into a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    /// A validator passed to `ProgramLinker::new` was compiled for the wrong
+    /// stage.
+    WrongShaderType {
+        expected: ShaderType,
+        found: ShaderType,
+    },
+    /// The stages were written against different ESSL versions.
+    VersionMismatch { vertex: i32, fragment: i32 },
+    /// The fragment shader uses a varying the vertex shader doesn't declare.
+    MissingVarying { name: String },
+    /// A varying is declared differently in the two stages.
+    VaryingMismatch { name: String },
+    /// A uniform is declared differently in the two stages.
+    UniformMismatch { name: String },
+    /// A uniform block is declared differently in the two stages.
+    UniformBlockMismatch { name: String },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LinkError::WrongShaderType { expected, found } => {
+                write!(
+                    f,
+                    "Expected a {:?} shader, got a {:?} shader",
+                    expected, found
+                )
+            }
+            LinkError::VersionMismatch { vertex, fragment } => write!(
+                f,
+                "Fragment shader version {} does not match vertex shader version {}",
+                fragment, vertex
+            ),
+            LinkError::MissingVarying { ref name } => write!(
+                f,
+                "Varying '{}' is used in the fragment shader but not declared in the vertex shader",
+                name
+            ),
+            LinkError::VaryingMismatch { ref name } => {
+                write!(f, "Varying '{}' differs between shaders", name)
+            }
+            LinkError::UniformMismatch { ref name } => {
+                write!(f, "Uniform '{}' differs between shaders", name)
+            }
+            LinkError::UniformBlockMismatch { ref name } => {
+                write!(f, "Uniform block '{}' differs between shaders", name)
+            }
+        }
+    }
+}
+
+impl error::Error for LinkError {}
+
+/// The interface of a linked program.
+#[derive(Clone, Debug)]
+pub struct LinkedProgram {
+    pub attributes: Vec<ShaderVariable>,
+    /// The uniforms of both stages, each listed once and marked as
+    /// statically used if either stage uses it.
+    pub uniforms: Vec<ShaderVariable>,
+    /// The uniform blocks of both stages, merged the same way as `uniforms`.
+    pub uniform_blocks: Vec<InterfaceBlock>,
+    /// The varyings written by the vertex shader that the fragment shader
+    /// reads.
+    pub varyings: Vec<ShaderVariable>,
+    pub output_variables: Vec<ShaderVariable>,
+}
+
+/// Checks a vertex and fragment shader against each other the way WebGL's
+/// `linkProgram` does.
+///
+/// Both validators must have compiled their shader with the `variables`
+/// option, otherwise there is nothing to check.
+pub struct ProgramLinker<'a> {
+    vertex: &'a ShaderValidator,
+    fragment: &'a ShaderValidator,
+}
+
+impl<'a> ProgramLinker<'a> {
+    pub fn new(vertex: &'a ShaderValidator, fragment: &'a ShaderValidator) -> Self {
+        ProgramLinker { vertex, fragment }
+    }
+
+    pub fn link(&self) -> Result<LinkedProgram, Vec<LinkError>> {
+        let mut errors = Vec::new();
+
+        for &(validator, expected) in &[
+            (self.vertex, ShaderType::Vertex),
+            (self.fragment, ShaderType::Fragment),
+        ] {
+            if validator.shader_type() != expected {
+                errors.push(LinkError::WrongShaderType {
+                    expected,
+                    found: validator.shader_type(),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let vertex_version = self.vertex.shader_version();
+        let fragment_version = self.fragment.shader_version();
+        if vertex_version != fragment_version {
+            errors.push(LinkError::VersionMismatch {
+                vertex: vertex_version,
+                fragment: fragment_version,
+            });
+        }
+
+        let varyings = self.link_varyings(vertex_version, &mut errors);
+        let uniforms = link_by_name(
+            self.vertex.uniforms(),
+            self.fragment.uniforms(),
+            |u| &u.name,
+            same_uniform,
+            |u, other| u.static_use |= other.static_use,
+            |name| LinkError::UniformMismatch { name },
+            &mut errors,
+        );
+        let uniform_blocks = link_by_name(
+            self.vertex.uniform_blocks(),
+            self.fragment.uniform_blocks(),
+            |b| &b.name,
+            same_interface_block,
+            |b, other| b.static_use |= other.static_use,
+            |name| LinkError::UniformBlockMismatch { name },
+            &mut errors,
+        );
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(LinkedProgram {
+            attributes: self.vertex.attributes(),
+            uniforms,
+            uniform_blocks,
+            varyings,
+            output_variables: self.fragment.output_variables(),
+        })
+    }
+
+    fn link_varyings(
+        &self,
+        shader_version: i32,
+        errors: &mut Vec<LinkError>,
+    ) -> Vec<ShaderVariable> {
+        let outputs = self.vertex.output_varyings();
+        let mut varyings = Vec::new();
+
+        for input in self.fragment.input_varyings() {
+            // Built-ins such as gl_FragCoord are not written by the vertex
+            // shader.
+            if input.is_built_in() {
+                continue;
+            }
+
+            // I/O blocks match by block name, other varyings by name.
+            let output = outputs.iter().find(|output| {
+                output.is_shader_io_block == input.is_shader_io_block
+                    && if input.is_shader_io_block {
+                        output.struct_name == input.struct_name
+                    } else {
+                        output.name == input.name
+                    }
+            });
+            match output {
+                Some(output) => {
+                    if same_varying(output, &input, shader_version) {
+                        varyings.push(output.clone());
+                    } else {
+                        errors.push(LinkError::VaryingMismatch { name: input.name });
+                    }
+                }
+                None if input.static_use => {
+                    errors.push(LinkError::MissingVarying { name: input.name })
+                }
+                None => {}
+            }
+        }
+        varyings
+    }
+}
+
+/// Merges the declarations of both stages, checking those declared in both
+/// with `same`.
+fn link_by_name<T, N, S, M, E>(
+    vertex: Vec<T>,
+    fragment: Vec<T>,
+    name: N,
+    same: S,
+    merge: M,
+    error: E,
+    errors: &mut Vec<LinkError>,
+) -> Vec<T>
+where
+    N: Fn(&T) -> &String,
+    S: Fn(&T, &T) -> bool,
+    M: Fn(&mut T, &T),
+    E: Fn(String) -> LinkError,
+{
+    let mut linked = vertex;
+    for declaration in fragment {
+        match linked.iter_mut().find(|l| name(l) == name(&declaration)) {
+            Some(existing) => {
+                if same(existing, &declaration) {
+                    merge(existing, &declaration);
+                } else {
+                    errors.push(error(name(&declaration).clone()));
+                }
+            }
+            None => linked.push(declaration),
+        }
+    }
+    linked
+}
+
+fn same_uniform(a: &ShaderVariable, b: &ShaderVariable) -> bool {
+    let (mut flat_a, mut flat_b) = (Vec::new(), Vec::new());
+    flatten(slice::from_ref(a), &mut flat_a);
+    flatten(slice::from_ref(b), &mut flat_b);
+    unsafe { GLSLangIsSameUniformAtLinkTime(flat_a.as_ptr(), flat_b.as_ptr()) != 0 }
+}
+
+fn same_varying(a: &ShaderVariable, b: &ShaderVariable, shader_version: i32) -> bool {
+    let (mut flat_a, mut flat_b) = (Vec::new(), Vec::new());
+    flatten(slice::from_ref(a), &mut flat_a);
+    flatten(slice::from_ref(b), &mut flat_b);
+    unsafe {
+        GLSLangIsSameVaryingAtLinkTime(flat_a.as_ptr(), flat_b.as_ptr(), shader_version as c_int)
+            != 0
+    }
+}
+
+fn same_interface_block(a: &InterfaceBlock, b: &InterfaceBlock) -> bool {
+    let (mut fields_a, mut fields_b) = (Vec::new(), Vec::new());
+    flatten(&a.fields, &mut fields_a);
+    flatten(&b.fields, &mut fields_b);
+    unsafe {
+        GLSLangIsSameInterfaceBlockAtLinkTime(
+            &flatten_block(a),
+            fields_a.as_ptr(),
+            fields_a.len(),
+            &flatten_block(b),
+            fields_b.as_ptr(),
+            fields_b.len(),
+        ) != 0
+    }
+}
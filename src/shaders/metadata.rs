@@ -0,0 +1,70 @@
+macro_rules! gl_enum {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident {
+            $($variant:ident = $value:expr,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            pub fn from_gl_enum(value: u32) -> Option<$name> {
+                match value {
+                    $($value => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn to_gl_enum(self) -> u32 {
+                match self {
+                    $($name::$variant => $value,)*
+                }
+            }
+        }
+    };
+}
+
+gl_enum! {
+    /// A primitive type declared by a geometry shader's `in` or `out`
+    /// layout qualifier.
+    pub enum GeometryPrimitive {
+        Points = 0x0000,
+        Lines = 0x0001,
+        LinesAdjacency = 0x000A,
+        Triangles = 0x0004,
+        TrianglesAdjacency = 0x000C,
+        LineStrip = 0x0003,
+        TriangleStrip = 0x0005,
+    }
+}
+
+gl_enum! {
+    /// The primitive a tessellation evaluation shader subdivides.
+    pub enum TessGenMode {
+        Triangles = 0x0004,
+        Quads = 0x0007,
+        Isolines = 0x8E7A,
+    }
+}
+
+gl_enum! {
+    /// How a tessellation evaluation shader spaces the vertices it generates.
+    pub enum TessGenSpacing {
+        Equal = 0x0202,
+        FractionalEven = 0x8E7C,
+        FractionalOdd = 0x8E7B,
+    }
+}
+
+gl_enum! {
+    /// The winding of triangles generated by a tessellation evaluation
+    /// shader.
+    pub enum TessGenVertexOrder {
+        Cw = 0x0900,
+        Ccw = 0x0901,
+    }
+}
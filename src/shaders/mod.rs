@@ -6,16 +6,58 @@ pub mod ffi {
     include!(concat!(env!("OUT_DIR"), "/glslang_glue_bindings.rs"));
 }
 
+mod block_layout;
+mod compile_options;
+mod diagnostics;
+mod driver_log;
+mod extensions;
+mod includes;
+mod linker;
+mod metadata;
+mod name_hashing;
+mod name_map;
+mod packing;
+mod preprocessor;
+mod resources;
+mod source_map;
+mod variables;
+
+pub use self::block_layout::{BlockLayoutInfo, BlockMemberInfo};
+pub use self::compile_options::CompileOptions;
+use self::diagnostics::parse_line;
+pub use self::diagnostics::{parse_info_log, Diagnostic, Severity};
+pub use self::extensions::{ExtensionBehavior, RequestedExtension};
+pub use self::includes::{
+    expand_includes, ExpandedSource, FileSystemResolver, IncludeError, IncludeResolver,
+};
+pub use self::linker::{LinkError, LinkedProgram, ProgramLinker};
+pub use self::metadata::{GeometryPrimitive, TessGenMode, TessGenSpacing, TessGenVertexOrder};
+pub use self::name_hashing::NameHasher;
+pub use self::name_map::NameMap;
+pub use self::packing::{pack_varyings, PackedVarying};
+pub use self::preprocessor::{
+    Directive, Macro, MacroError, PreprocessedSource, Preprocessor, Token, TokenKind,
+};
+pub use self::resources::{BuiltInResourcesBuilder, Extension, Limit};
+pub use self::source_map::{SourceLocation, SourceMap};
+pub use self::variables::{BlockLayout, BlockType, InterfaceBlock, Interpolation, ShaderVariable};
+
 use self::ffi::ShShaderOutput::*;
 use self::ffi::ShShaderSpec::*;
 use self::ffi::*;
+use self::variables::{InterfaceBlockCollector, VariableCollector};
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::default;
+use std::error;
 use std::ffi::CStr;
 use std::ffi::CString;
+use std::fmt;
 use std::mem::MaybeUninit;
 use std::os::raw::c_char;
+use std::os::raw::c_int;
 use std::os::raw::c_void;
 use std::slice;
 use std::str;
@@ -43,12 +85,42 @@ pub trait AsAngleEnum {
     fn as_angle_enum(&self) -> u32;
 }
 
+/// The pipeline stage a shader is compiled for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderType {
+    Vertex,
+    Fragment,
+    Compute,
+    Geometry,
+    TessControl,
+    TessEvaluation,
+}
+
+impl AsAngleEnum for ShaderType {
+    #[inline]
+    fn as_angle_enum(&self) -> u32 {
+        match *self {
+            ShaderType::Vertex => 0x8B31,         // GL_VERTEX_SHADER
+            ShaderType::Fragment => 0x8B30,       // GL_FRAGMENT_SHADER
+            ShaderType::Compute => 0x91B9,        // GL_COMPUTE_SHADER
+            ShaderType::Geometry => 0x8DD9,       // GL_GEOMETRY_SHADER
+            ShaderType::TessControl => 0x8E88,    // GL_TESS_CONTROL_SHADER
+            ShaderType::TessEvaluation => 0x8E87, // GL_TESS_EVALUATION_SHADER
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ShaderSpec {
     Gles2,
     WebGL,
     Gles3,
     WebGL2,
+    Gles31,
     WebGL3,
+    Gles32,
+    GlCore,
+    GlCompatibility,
 }
 
 impl AsAngleEnum for ShaderSpec {
@@ -59,11 +131,57 @@ impl AsAngleEnum for ShaderSpec {
             ShaderSpec::WebGL => SH_WEBGL_SPEC,
             ShaderSpec::Gles3 => SH_GLES3_SPEC,
             ShaderSpec::WebGL2 => SH_WEBGL2_SPEC,
+            ShaderSpec::Gles31 => SH_GLES3_1_SPEC,
             ShaderSpec::WebGL3 => SH_WEBGL3_SPEC,
+            ShaderSpec::Gles32 => SH_GLES3_2_SPEC,
+            ShaderSpec::GlCore => SH_GL_CORE_SPEC,
+            ShaderSpec::GlCompatibility => SH_GL_COMPATIBILITY_SPEC,
         }) as u32
     }
 }
 
+impl ShaderSpec {
+    /// Whether this spec is based on the WebGL spec, see `IsWebGLBasedSpec`.
+    #[inline]
+    pub fn is_webgl(&self) -> bool {
+        matches!(
+            *self,
+            ShaderSpec::WebGL | ShaderSpec::WebGL2 | ShaderSpec::WebGL3
+        )
+    }
+
+    /// Whether this spec takes desktop GLSL input, see `IsDesktopGLSpec`.
+    #[inline]
+    pub fn is_desktop(&self) -> bool {
+        matches!(*self, ShaderSpec::GlCore | ShaderSpec::GlCompatibility)
+    }
+
+    /// Whether shaders of the given type can be compiled against this spec.
+    ///
+    /// Geometry and tessellation shaders are accepted for GLES 3.1, where
+    /// they are available through `EXT_geometry_shader` and
+    /// `EXT_tessellation_shader`.
+    pub fn supports(&self, shader_type: ShaderType) -> bool {
+        match shader_type {
+            ShaderType::Vertex | ShaderType::Fragment => true,
+            ShaderType::Compute => !matches!(
+                *self,
+                ShaderSpec::Gles2 | ShaderSpec::WebGL | ShaderSpec::Gles3 | ShaderSpec::WebGL2
+            ),
+            ShaderType::Geometry | ShaderType::TessControl | ShaderType::TessEvaluation => {
+                matches!(
+                    *self,
+                    ShaderSpec::Gles31
+                        | ShaderSpec::Gles32
+                        | ShaderSpec::GlCore
+                        | ShaderSpec::GlCompatibility
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Output {
     Essl,
     Glsl,
@@ -79,6 +197,12 @@ pub enum Output {
     Glsl430Core,
     Glsl440Core,
     Glsl450Core,
+    /// HLSL for Direct3D 9.
+    Hlsl30,
+    /// HLSL for Direct3D 11.
+    Hlsl41,
+    /// HLSL for Direct3D 11 on feature level 9_3 hardware.
+    Hlsl40Fl93,
 }
 
 impl AsAngleEnum for Output {
@@ -99,10 +223,21 @@ impl AsAngleEnum for Output {
             Output::Glsl430Core => SH_GLSL_430_CORE_OUTPUT,
             Output::Glsl440Core => SH_GLSL_440_CORE_OUTPUT,
             Output::Glsl450Core => SH_GLSL_450_CORE_OUTPUT,
+            Output::Hlsl30 => SH_HLSL_3_0_OUTPUT,
+            Output::Hlsl41 => SH_HLSL_4_1_OUTPUT,
+            Output::Hlsl40Fl93 => SH_HLSL_4_0_FL9_3_OUTPUT,
         }) as u32
     }
 }
 
+impl Output {
+    /// Whether this is one of the HLSL outputs, see `IsOutputHLSL`.
+    #[inline]
+    pub fn is_hlsl(&self) -> bool {
+        matches!(*self, Output::Hlsl30 | Output::Hlsl41 | Output::Hlsl40Fl93)
+    }
+}
+
 pub type BuiltInResources = ShBuiltInResources;
 
 impl default::Default for BuiltInResources {
@@ -122,30 +257,100 @@ impl BuiltInResources {
     }
 }
 
-pub type CompileOptions = ShCompileOptions;
-
-impl CompileOptions {
-    pub fn mozangle() -> Self {
-        let mut options = unsafe { CompileOptions::new() };
-        options.set_objectCode(1);
-        options.set_variables(1); // For uniform_name_map()
-        options.set_emulateAbsIntFunction(1); // To workaround drivers
-        options.set_emulateIsnanFloatFunction(1); // To workaround drivers
-        options.set_emulateAtan2FloatFunction(1); // To workaround drivers
-        options.set_clampIndirectArrayBounds(1);
-        options.set_initGLPosition(1);
-        options.set_enforcePackingRestrictions(1);
-        options.set_limitExpressionComplexity(1);
-        options.set_limitCallStackDepth(1);
-        // Todo(Mortimer): Add SH_TIMING_RESTRICTIONS to options when the implementations gets better
-        // Right now SH_TIMING_RESTRICTIONS is experimental
-        // and doesn't support user callable functions in shaders
-        options
+/// The ways in which constructing a compiler or compiling a shader can fail.
+#[derive(Clone, Debug)]
+pub enum CompileError {
+    /// ANGLE could not construct a compiler for the requested configuration.
+    CompilerConstruction,
+    /// The shader stage does not exist in the requested spec.
+    UnsupportedShaderType {
+        shader_type: ShaderType,
+        spec: ShaderSpec,
+    },
+    /// A source string contains a nul byte and cannot be passed to ANGLE.
+    InvalidInput {
+        /// Index of the offending source string.
+        string: usize,
+        /// Byte offset of the nul byte in that string.
+        position: usize,
+    },
+    /// An `#include` directive couldn't be expanded.
+    Include(IncludeError),
+    /// A source map was requested for an output without `#line` directives.
+    SourceMapUnsupported { output: Output },
+    /// The translator rejected the shader.
+    Rejected {
+        info_log: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+impl CompileError {
+    /// The translator's info log, if the shader got as far as the translator.
+    pub fn info_log(&self) -> Option<&str> {
+        match *self {
+            CompileError::Rejected { ref info_log, .. } => Some(info_log),
+            _ => None,
+        }
+    }
+
+    /// The diagnostics parsed from the info log, if any.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match *self {
+            CompileError::Rejected {
+                ref diagnostics, ..
+            } => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CompileError::CompilerConstruction => f.write_str("Couldn't construct compiler"),
+            CompileError::UnsupportedShaderType { shader_type, spec } => write!(
+                f,
+                "{:?} shaders are not supported by the {:?} spec",
+                shader_type, spec
+            ),
+            CompileError::InvalidInput { string, position } => write!(
+                f,
+                "Found invalid characters in shader string {} at byte {}",
+                string, position
+            ),
+            CompileError::Include(ref error) => error.fmt(f),
+            CompileError::SourceMapUnsupported { output } => write!(
+                f,
+                "{:?} output has no line directives to build a source map from",
+                output
+            ),
+            CompileError::Rejected { ref info_log, .. } => {
+                f.write_str("Couldn't compile shader")?;
+                if !info_log.is_empty() {
+                    write!(f, ":\n{}", info_log.trim_end())?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
+impl error::Error for CompileError {}
+
 pub struct ShaderValidator {
     handle: ShHandle,
+    shader_type: ShaderType,
+    output: Output,
+    name_hasher: Option<NameHasher>,
+    /// The line each source string started on, when the last compilation
+    /// concatenated them.
+    line_starts: RefCell<Vec<u32>>,
+    /// The names of the source strings of the last compilation, if it had
+    /// any.
+    source_names: RefCell<Vec<String>>,
+    /// The `#extension` directives of the last compilation.
+    requested_extensions: RefCell<Vec<RequestedExtension>>,
 }
 
 impl ShaderValidator {
@@ -153,67 +358,245 @@ impl ShaderValidator {
     /// NB: To call this you should have called first
     /// initialize()
     pub fn new(
-        shader_type: u32,
+        shader_type: ShaderType,
+        spec: ShaderSpec,
+        output: Output,
+        resources: &BuiltInResources,
+    ) -> Result<ShaderValidator, CompileError> {
+        Self::construct(shader_type, spec, output, resources, None)
+    }
+
+    /// Create a new ShaderValidator that hashes user-defined identifiers with
+    /// `hasher` instead of the `HashFunction` of `resources`.
+    ///
+    /// `name_hashing_map` returns the names that were hashed by the last
+    /// compilation.
+    pub fn with_name_hasher(
+        shader_type: ShaderType,
         spec: ShaderSpec,
         output: Output,
         resources: &BuiltInResources,
-    ) -> Option<ShaderValidator> {
+        hasher: NameHasher,
+    ) -> Result<ShaderValidator, CompileError> {
+        Self::construct(shader_type, spec, output, resources, Some(hasher))
+    }
+
+    fn construct(
+        shader_type: ShaderType,
+        spec: ShaderSpec,
+        output: Output,
+        resources: &BuiltInResources,
+        name_hasher: Option<NameHasher>,
+    ) -> Result<ShaderValidator, CompileError> {
+        let mut resources = *resources;
+        if name_hasher.is_some() {
+            resources.HashFunction = Some(name_hashing::hash_name);
+        }
+
+        if !spec.supports(shader_type) {
+            return Err(CompileError::UnsupportedShaderType { shader_type, spec });
+        }
+
         // GLSLangConstructCompiler is non-thread safe because it internally calls TCache::getType()
         // which writes/reads a std::map<T> with no locks.
         let _guard = CONSTRUCT_COMPILER_LOCK.lock().unwrap();
         let handle = unsafe {
             GLSLangConstructCompiler(
-                shader_type,
+                shader_type.as_angle_enum(),
                 spec.as_angle_enum(),
                 output.as_angle_enum(),
-                resources,
+                &resources,
             )
         };
 
         if handle.is_null() {
-            return None;
+            return Err(CompileError::CompilerConstruction);
         }
 
-        Some(ShaderValidator { handle: handle })
+        Ok(ShaderValidator {
+            handle,
+            shader_type,
+            output,
+            name_hasher,
+            line_starts: RefCell::new(Vec::new()),
+            source_names: RefCell::new(Vec::new()),
+            requested_extensions: RefCell::new(Vec::new()),
+        })
     }
 
     #[inline]
     pub fn for_webgl(
-        shader_type: u32,
+        shader_type: ShaderType,
         output: Output,
         resources: &BuiltInResources,
-    ) -> Option<ShaderValidator> {
+    ) -> Result<ShaderValidator, CompileError> {
         Self::new(shader_type, ShaderSpec::WebGL, output, resources)
     }
 
     #[inline]
     pub fn for_webgl2(
-        shader_type: u32,
+        shader_type: ShaderType,
         output: Output,
         resources: &BuiltInResources,
-    ) -> Option<ShaderValidator> {
+    ) -> Result<ShaderValidator, CompileError> {
         Self::new(shader_type, ShaderSpec::WebGL2, output, resources)
     }
 
-    pub fn compile(&self, strings: &[&str], options: ShCompileOptions) -> Result<(), &'static str> {
+    #[inline]
+    pub fn shader_type(&self) -> ShaderType {
+        self.shader_type
+    }
+
+    /// Returns the version of the last compiled shader, for example 100 or
+    /// 300.
+    #[inline]
+    pub fn shader_version(&self) -> i32 {
+        unsafe { GLSLangGetShaderVersion(self.handle) }
+    }
+
+    pub fn compile(&self, strings: &[&str], options: CompileOptions) -> Result<(), CompileError> {
+        self.compile_strings(strings, options, Vec::new(), Vec::new())
+    }
+
+    /// Compiles `(name, source)` pairs, such as `("common.glsl", ..)`, so
+    /// that diagnostics, the info log and source maps show the names instead
+    /// of the indices of the source strings.
+    pub fn compile_named(
+        &self,
+        sources: &[(&str, &str)],
+        options: CompileOptions,
+    ) -> Result<(), CompileError> {
+        let strings: Vec<_> = sources.iter().map(|&(_, source)| source).collect();
+        self.compile_strings(&strings, options, Vec::new(), names(sources))
+    }
+
+    /// Compiles `source`, the contents of the file `name`, after expanding
+    /// its `#include` directives with `resolver`, see `expand_includes`.
+    ///
+    /// Diagnostics, the info log and source maps show the names of the
+    /// files given by the resolver. With the `source_path` option, `name` is
+    /// the path.
+    pub fn compile_with_includes<R: IncludeResolver + ?Sized>(
+        &self,
+        name: &str,
+        source: &str,
+        resolver: &mut R,
+        options: CompileOptions,
+    ) -> Result<(), CompileError> {
+        let expanded = expand_includes(resolver, name, source).map_err(CompileError::Include)?;
+        let mut strings = Vec::with_capacity(2);
+        if options.sourcePath() != 0 {
+            strings.push(name);
+        }
+        strings.push(&*expanded.source);
+        self.compile_strings(&strings, options, Vec::new(), expanded.names)
+    }
+
+    /// `compile_named` with the source map of `compile_with_source_map`.
+    pub fn compile_named_with_source_map(
+        &self,
+        sources: &[(&str, &str)],
+        options: CompileOptions,
+    ) -> Result<(), CompileError> {
+        let strings: Vec<_> = sources.iter().map(|&(_, source)| source).collect();
+        self.compile_joined(&strings, options, names(sources))
+    }
+
+    /// Compiles the shader with line directives, so that `source_map` can
+    /// tell where each line of the object code came from.
+    ///
+    /// ANGLE only writes line directives for the HLSL outputs, so this fails
+    /// with `SourceMapUnsupported` for the others.
+    ///
+    /// The strings are compiled as a single one, since the directives don't
+    /// name source strings, which makes `__FILE__` always 0. A newline is
+    /// added after strings that don't end with one, so that each line comes
+    /// from a single string. Diagnostics and the info log still refer to the
+    /// original strings.
+    pub fn compile_with_source_map(
+        &self,
+        strings: &[&str],
+        options: CompileOptions,
+    ) -> Result<(), CompileError> {
+        self.compile_joined(strings, options, Vec::new())
+    }
+
+    fn compile_joined(
+        &self,
+        strings: &[&str],
+        options: CompileOptions,
+        names: Vec<String>,
+    ) -> Result<(), CompileError> {
+        if !self.output.is_hlsl() {
+            return Err(CompileError::SourceMapUnsupported {
+                output: self.output,
+            });
+        }
+        // With the `source_path` option, the first string is the path.
+        let sources = if options.sourcePath() != 0 && !strings.is_empty() {
+            1
+        } else {
+            0
+        };
+        for (i, s) in strings.iter().enumerate() {
+            if let Some(position) = s.find('\0') {
+                return Err(CompileError::InvalidInput {
+                    string: i,
+                    position,
+                });
+            }
+        }
+        let mut joined = String::new();
+        for s in &strings[sources..] {
+            joined.push_str(s);
+            if !s.ends_with('\n') {
+                joined.push('\n');
+            }
+        }
+        let mut joined_strings = strings[..sources].to_vec();
+        joined_strings.push(&joined);
+        self.compile_strings(
+            &joined_strings,
+            options.line_directives(true),
+            source_map::line_starts(&strings[sources..]),
+            names,
+        )
+    }
+
+    fn compile_strings(
+        &self,
+        strings: &[&str],
+        options: CompileOptions,
+        line_starts: Vec<u32>,
+        names: Vec<String>,
+    ) -> Result<(), CompileError> {
         let mut cstrings = Vec::with_capacity(strings.len());
 
-        for s in strings.iter() {
-            cstrings.push(CString::new(*s).map_err(|_| "Found invalid characters")?)
+        for (i, s) in strings.iter().enumerate() {
+            cstrings.push(CString::new(*s).map_err(|e| CompileError::InvalidInput {
+                string: i,
+                position: e.nul_position(),
+            })?)
         }
 
         let cptrs: Vec<_> = cstrings.iter().map(|s| s.as_ptr()).collect();
+        *self.line_starts.borrow_mut() = line_starts;
+        *self.source_names.borrow_mut() = names;
+        let _hasher = self.name_hasher.as_ref().map(NameHasher::install);
 
-        if unsafe {
-            GLSLangCompile(
-                self.handle,
-                cptrs.as_ptr() as *const *const c_char,
-                cstrings.len(),
-                &options as *const _,
-            )
-        } == 0
-        {
-            return Err("Couldn't compile shader");
+        let compiled =
+            unsafe { GLSLangCompile(self.handle, cptrs.as_ptr(), cstrings.len(), &*options) };
+        let sources = if options.sourcePath() != 0 && !cptrs.is_empty() {
+            &cptrs[1..]
+        } else {
+            &cptrs[..]
+        };
+        self.record_extension_directives(sources);
+        if compiled == 0 {
+            return Err(CompileError::Rejected {
+                diagnostics: self.diagnostics(),
+                info_log: self.info_log(),
+            });
         }
         Ok(())
     }
@@ -225,14 +608,72 @@ impl ShaderValidator {
         }
     }
 
+    /// Returns the info log of the last compilation, with locations in
+    /// terms of the original source strings and their names, see
+    /// `compile_named` and `compile_with_source_map`.
     pub fn info_log(&self) -> String {
+        let info_log = self.raw_info_log();
+        if self.line_starts.borrow().is_empty() && self.source_names.borrow().is_empty() {
+            return info_log;
+        }
+        let mut lines = Vec::new();
+        for line in info_log.split('\n') {
+            match parse_line(line) {
+                Some(mut diagnostic) => {
+                    self.resolve(&mut diagnostic);
+                    lines.push(diagnostic.to_string());
+                }
+                None => lines.push(line.to_owned()),
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn raw_info_log(&self) -> String {
         unsafe {
             let c_str = CStr::from_ptr(GLSLangGetInfoLog(self.handle));
             c_str.to_string_lossy().into_owned()
         }
     }
 
-    pub fn compile_and_translate(&self, strings: &[&str]) -> Result<String, &'static str> {
+    /// Returns the messages of the last compilation, including warnings
+    /// emitted for shaders that compiled successfully.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = parse_info_log(&self.raw_info_log());
+        for diagnostic in &mut diagnostics {
+            self.resolve(diagnostic);
+        }
+        diagnostics
+    }
+
+    /// Points `diagnostic` at the original source strings.
+    fn resolve(&self, diagnostic: &mut Diagnostic) {
+        let line_starts = self.line_starts.borrow();
+        if let (false, Some(line)) = (line_starts.is_empty(), diagnostic.line) {
+            let location = source_map::locate(&line_starts, line);
+            diagnostic.source_string = Some(location.string);
+            diagnostic.line = Some(location.line);
+        }
+        if let Some(source_string) = diagnostic.source_string {
+            diagnostic.source_name = self
+                .source_names
+                .borrow()
+                .get(source_string as usize)
+                .cloned();
+        }
+    }
+
+    /// Returns where each line of the object code came from, see
+    /// `compile_with_source_map`.
+    pub fn source_map(&self) -> SourceMap {
+        SourceMap::new(
+            &self.object_code(),
+            &self.line_starts.borrow(),
+            self.source_names.borrow().clone(),
+        )
+    }
+
+    pub fn compile_and_translate(&self, strings: &[&str]) -> Result<String, CompileError> {
         let options = CompileOptions::mozangle();
         self.compile(strings, options)?;
         Ok(self.object_code())
@@ -292,9 +733,459 @@ impl ShaderValidator {
         closure.map
     }
 
+    /// Returns a map from the user-defined names of the last compiled shader
+    /// to the hashed names they were given in the object code.
+    ///
+    /// Without a `HashFunction` in the resources or a hasher given to
+    /// `with_name_hasher`, the names are only prefixed with `_u`, and the map
+    /// records that instead.
+    pub fn name_hashing_map(&self) -> HashMap<String, String> {
+        unsafe extern "C" fn each(
+            closure: *mut c_void,
+            name: *const c_char,
+            name_len: usize,
+            hashed: *const c_char,
+            hashed_len: usize,
+        ) {
+            let map = &mut *(closure as *mut HashMap<String, String>);
+            map.insert(to_string(name, name_len), to_string(hashed, hashed_len));
+        }
+
+        let mut map = HashMap::new();
+        unsafe {
+            GLSLangIterNameHashingMap(
+                self.handle,
+                Some(each),
+                &mut map as *mut HashMap<String, String> as *mut c_void,
+            )
+        }
+        map
+    }
+
+    /// Returns the original and mapped names of every uniform, attribute,
+    /// varying, output variable and interface block of the last compiled
+    /// shader, their members, and the types of its shader I/O blocks.
+    ///
+    /// The map is empty if the `variables` option wasn't specified.
+    pub fn name_map(&self) -> NameMap {
+        let mut map = NameMap::new();
+        map.add_variables(&self.uniforms());
+        map.add_variables(&self.attributes());
+        map.add_variables(&self.input_varyings());
+        map.add_variables(&self.output_varyings());
+        map.add_variables(&self.output_variables());
+        map.add_interface_blocks(&self.interface_blocks());
+        map
+    }
+
+    /// Returns the uniforms declared by the shader, with struct uniforms
+    /// keeping their members in `fields`.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn uniforms(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterUniforms(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the attributes declared by a vertex shader. `location` is set
+    /// for attributes with an explicit `layout(location = N)`.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn attributes(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterAttributes(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns every varying of the shader, both inputs and outputs.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn varyings(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterVaryings(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the varyings the shader reads from the previous stage.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn input_varyings(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterInputVaryings(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the varyings the shader writes for the next stage.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn output_varyings(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterOutputVaryings(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the outputs written by a fragment shader, including
+    /// `gl_FragColor` and `gl_FragData` for ESSL 1.00 shaders.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn output_variables(&self) -> Vec<ShaderVariable> {
+        let mut collector = VariableCollector::new();
+        unsafe {
+            GLSLangIterOutputVariables(
+                self.handle,
+                Some(VariableCollector::each),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns every interface block of the shader, both uniform blocks and
+    /// shader storage blocks.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn interface_blocks(&self) -> Vec<InterfaceBlock> {
+        let mut collector = InterfaceBlockCollector::new();
+        unsafe {
+            GLSLangIterInterfaceBlocks(
+                self.handle,
+                Some(InterfaceBlockCollector::each_block),
+                Some(InterfaceBlockCollector::each_field),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the uniform blocks of the shader.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn uniform_blocks(&self) -> Vec<InterfaceBlock> {
+        let mut collector = InterfaceBlockCollector::new();
+        unsafe {
+            GLSLangIterUniformBlocks(
+                self.handle,
+                Some(InterfaceBlockCollector::each_block),
+                Some(InterfaceBlockCollector::each_field),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
+    /// Returns the shader storage blocks of the shader.
+    ///
+    /// The list is empty if the `variables` option wasn't specified.
+    pub fn shader_storage_blocks(&self) -> Vec<InterfaceBlock> {
+        let mut collector = InterfaceBlockCollector::new();
+        unsafe {
+            GLSLangIterShaderStorageBlocks(
+                self.handle,
+                Some(InterfaceBlockCollector::each_block),
+                Some(InterfaceBlockCollector::each_field),
+                collector.closure(),
+            )
+        }
+        collector.finish()
+    }
+
     pub fn get_num_unpacked_varying_vectors(&self) -> i32 {
         unsafe { GLSLangGetNumUnpackedVaryingVectors(self.handle) }
     }
+
+    /// Returns the `local_size_x`, `local_size_y` and `local_size_z` declared
+    /// by a compute shader, with omitted dimensions defaulting to 1.
+    ///
+    /// Returns `None` for other stages and before a successful compile.
+    pub fn compute_local_size(&self) -> Option<[u32; 3]> {
+        if self.shader_type != ShaderType::Compute {
+            return None;
+        }
+        let mut size = [0; 3];
+        unsafe { GLSLangGetComputeShaderLocalGroupSize(self.handle, size.as_mut_ptr()) }
+        if size.iter().any(|&s| s < 1) {
+            return None;
+        }
+        Some([size[0] as u32, size[1] as u32, size[2] as u32])
+    }
+
+    /// Returns the number of bytes of `shared` variables a compute shader
+    /// declares, or `None` for other stages.
+    ///
+    /// The size is 0 if the `variables` option wasn't specified.
+    pub fn shared_memory_size(&self) -> Option<u32> {
+        if self.shader_type != ShaderType::Compute {
+            return None;
+        }
+        Some(unsafe { GLSLangGetShaderSharedMemorySize(self.handle) })
+    }
+
+    /// Returns the primitive type of a geometry shader's `in` layout
+    /// qualifier, or `None` for other stages or if it wasn't declared.
+    pub fn geometry_input_primitive(&self) -> Option<GeometryPrimitive> {
+        if self.shader_type != ShaderType::Geometry {
+            return None;
+        }
+        self.get_enum(GLSLangGetGeometryShaderInputPrimitiveType)
+            .and_then(GeometryPrimitive::from_gl_enum)
+    }
+
+    /// Returns the primitive type of a geometry shader's `out` layout
+    /// qualifier, or `None` for other stages or if it wasn't declared.
+    pub fn geometry_output_primitive(&self) -> Option<GeometryPrimitive> {
+        if self.shader_type != ShaderType::Geometry {
+            return None;
+        }
+        self.get_enum(GLSLangGetGeometryShaderOutputPrimitiveType)
+            .and_then(GeometryPrimitive::from_gl_enum)
+    }
+
+    /// Returns the number of times a geometry shader is invoked per input
+    /// primitive, which defaults to 1, or `None` for other stages.
+    pub fn geometry_invocations(&self) -> Option<u32> {
+        if self.shader_type != ShaderType::Geometry {
+            return None;
+        }
+        Some(unsafe { GLSLangGetGeometryShaderInvocations(self.handle) } as u32)
+    }
+
+    /// Returns the `max_vertices` a geometry shader declared, or `None` for
+    /// other stages or if it wasn't declared.
+    pub fn geometry_max_vertices(&self) -> Option<u32> {
+        if self.shader_type != ShaderType::Geometry {
+            return None;
+        }
+        let max_vertices = unsafe { GLSLangGetGeometryShaderMaxVertices(self.handle) };
+        if max_vertices < 0 {
+            None
+        } else {
+            Some(max_vertices as u32)
+        }
+    }
+
+    /// Returns the size of the output patch a tessellation control shader
+    /// declared with `vertices`, or `None` for other stages or if it wasn't
+    /// declared.
+    pub fn tess_control_vertices(&self) -> Option<u32> {
+        if self.shader_type != ShaderType::TessControl {
+            return None;
+        }
+        let vertices = unsafe { GLSLangGetTessControlShaderVertices(self.handle) };
+        if vertices <= 0 {
+            None
+        } else {
+            Some(vertices as u32)
+        }
+    }
+
+    /// Returns the primitive mode of a tessellation evaluation shader, or
+    /// `None` for other stages or if it wasn't declared.
+    pub fn tess_gen_mode(&self) -> Option<TessGenMode> {
+        if self.shader_type != ShaderType::TessEvaluation {
+            return None;
+        }
+        self.get_enum(GLSLangGetTessGenMode)
+            .and_then(TessGenMode::from_gl_enum)
+    }
+
+    /// Returns the vertex spacing of a tessellation evaluation shader, or
+    /// `None` for other stages or if it wasn't declared.
+    pub fn tess_gen_spacing(&self) -> Option<TessGenSpacing> {
+        if self.shader_type != ShaderType::TessEvaluation {
+            return None;
+        }
+        self.get_enum(GLSLangGetTessGenSpacing)
+            .and_then(TessGenSpacing::from_gl_enum)
+    }
+
+    /// Returns the vertex order of a tessellation evaluation shader, or
+    /// `None` for other stages or if it wasn't declared.
+    pub fn tess_gen_vertex_order(&self) -> Option<TessGenVertexOrder> {
+        if self.shader_type != ShaderType::TessEvaluation {
+            return None;
+        }
+        self.get_enum(GLSLangGetTessGenVertexOrder)
+            .and_then(TessGenVertexOrder::from_gl_enum)
+    }
+
+    /// Returns whether a tessellation evaluation shader declared
+    /// `point_mode`, or `None` for other stages.
+    pub fn tess_gen_point_mode(&self) -> Option<bool> {
+        if self.shader_type != ShaderType::TessEvaluation {
+            return None;
+        }
+        Some(unsafe { GLSLangHasTessGenPointMode(self.handle) } != 0)
+    }
+
+    fn get_enum(&self, getter: unsafe extern "C" fn(ShHandle, *mut u32) -> c_int) -> Option<u32> {
+        let mut value = 0;
+        if unsafe { getter(self.handle, &mut value) } == 0 {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Returns a map from uniform name to the register assigned to it in the
+    /// default uniform block, including samplers extracted from structs.
+    ///
+    /// The map is empty unless the shader was translated to HLSL.
+    pub fn uniform_register_map(&self) -> HashMap<String, u32> {
+        unsafe extern "C" fn each_c(
+            closure: *mut c_void,
+            name: *const c_char,
+            name_len: usize,
+            register: u32,
+        ) {
+            let map = &mut *(closure as *mut HashMap<String, u32>);
+            map.insert(to_string(name, name_len), register);
+        }
+
+        let mut map = HashMap::new();
+        unsafe {
+            GLSLangIterUniformRegisterMap(
+                self.handle,
+                Some(each_c),
+                &mut map as *mut HashMap<String, u32> as *mut c_void,
+            )
+        }
+        map
+    }
+
+    /// Returns the register assigned to the named uniform block, if the
+    /// shader was translated to HLSL and declares such a block.
+    pub fn uniform_block_register(&self, name: &str) -> Option<u32> {
+        let mut index = 0;
+        let found = unsafe {
+            GLSLangGetUniformBlockRegister(
+                self.handle,
+                name.as_ptr() as *const c_char,
+                name.len(),
+                &mut index,
+            )
+        };
+        if found == 0 {
+            None
+        } else {
+            Some(index)
+        }
+    }
+
+    /// Returns the register assigned to the named shader storage block, if
+    /// the shader was translated to HLSL and declares such a block.
+    pub fn shader_storage_block_register(&self, name: &str) -> Option<u32> {
+        let mut index = 0;
+        let found = unsafe {
+            GLSLangGetShaderStorageBlockRegister(
+                self.handle,
+                name.as_ptr() as *const c_char,
+                name.len(),
+                &mut index,
+            )
+        };
+        if found == 0 {
+            None
+        } else {
+            Some(index)
+        }
+    }
+
+    /// Whether the HLSL translation of the named uniform block is a
+    /// `StructuredBuffer` rather than a constant buffer.
+    pub fn uniform_block_uses_structured_buffer(&self, name: &str) -> bool {
+        unsafe {
+            GLSLangShouldUniformBlockUseStructuredBuffer(
+                self.handle,
+                name.as_ptr() as *const c_char,
+                name.len(),
+            ) != 0
+        }
+    }
+
+    /// Returns the uniform blocks whose HLSL translation is known to be slow
+    /// to compile with FXC.
+    pub fn slow_compiling_uniform_blocks(&self) -> HashSet<String> {
+        unsafe extern "C" fn each_c(closure: *mut c_void, name: *const c_char, name_len: usize) {
+            let set = &mut *(closure as *mut HashSet<String>);
+            set.insert(to_string(name, name_len));
+        }
+
+        let mut set = HashSet::new();
+        unsafe {
+            GLSLangIterSlowCompilingUniformBlocks(
+                self.handle,
+                Some(each_c),
+                &mut set as *mut HashSet<String> as *mut c_void,
+            )
+        }
+        set
+    }
+
+    /// Returns the first of the registers reserved for `image2D`,
+    /// `iimage2D` and `uimage2D` variables, if the shader was translated to
+    /// HLSL.
+    pub fn image2d_register_index(&self) -> Option<u32> {
+        let index = unsafe { GLSLangGetImage2DRegisterIndex(self.handle) };
+        if index < 0 {
+            None
+        } else {
+            Some(index as u32)
+        }
+    }
+
+    /// Same as `image2d_register_index`, for `readonly` image variables.
+    pub fn readonly_image2d_register_index(&self) -> Option<u32> {
+        let index = unsafe { GLSLangGetReadonlyImage2DRegisterIndex(self.handle) };
+        if index < 0 {
+            None
+        } else {
+            Some(index as u32)
+        }
+    }
+}
+
+/// The names of the `(name, source)` pairs given to `compile_named`.
+fn names(sources: &[(&str, &str)]) -> Vec<String> {
+    sources.iter().map(|&(name, _)| name.to_owned()).collect()
+}
+
+/// Copies a string handed out by the glue code.
+///
+/// Safety: code in or called from this function must not unwind into C++, so
+/// non-UTF-8 names are converted lossily rather than panicking.
+unsafe fn to_string(ptr: *const c_char, len: usize) -> String {
+    String::from_utf8_lossy(slice::from_raw_parts(ptr as *const u8, len)).into_owned()
 }
 
 impl Drop for ShaderValidator {
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::os::raw::c_char;
+use std::slice;
+use std::sync::Arc;
+
+type HashFn = dyn Fn(&[u8]) -> u64 + Send + Sync;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<HashFn>>> = RefCell::new(None);
+}
+
+/// A strategy for hashing the user-defined identifiers of a shader, which
+/// are then written to the translated shader as `webgl_` followed by the
+/// hash in hexadecimal.
+///
+/// ANGLE only takes a bare function pointer, so the hasher is installed for
+/// the current thread while `ShaderValidator::compile` runs.
+#[derive(Clone)]
+pub struct NameHasher {
+    hash: Arc<HashFn>,
+}
+
+impl NameHasher {
+    /// Hashes identifiers with `hash`, which must not panic.
+    pub fn new<F>(hash: F) -> Self
+    where
+        F: Fn(&[u8]) -> u64 + Send + Sync + 'static,
+    {
+        NameHasher {
+            hash: Arc::new(hash),
+        }
+    }
+
+    /// Hashes identifiers with the hashers built by `build_hasher`.
+    ///
+    /// With a `RandomState`, for example, each origin can be given its own
+    /// state so that it sees different names for the same shader, while
+    /// clones of a state keep giving the same names.
+    pub fn from_build_hasher<B>(build_hasher: B) -> Self
+    where
+        B: BuildHasher + Send + Sync + 'static,
+    {
+        NameHasher::new(move |name| {
+            let mut hasher = build_hasher.build_hasher();
+            hasher.write(name);
+            hasher.finish()
+        })
+    }
+
+    #[inline]
+    pub fn hash(&self, name: &[u8]) -> u64 {
+        (self.hash)(name)
+    }
+
+    /// Makes this hasher the one `hash_name` forwards to until the returned
+    /// guard is dropped.
+    pub(super) fn install(&self) -> InstalledNameHasher {
+        let previous = CURRENT.with(|current| current.replace(Some(self.hash.clone())));
+        InstalledNameHasher { previous }
+    }
+}
+
+impl fmt::Debug for NameHasher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("NameHasher")
+    }
+}
+
+pub(super) struct InstalledNameHasher {
+    previous: Option<Arc<HashFn>>,
+}
+
+impl Drop for InstalledNameHasher {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// The `HashFunction` given to ANGLE for validators with a `NameHasher`.
+pub(super) unsafe extern "C" fn hash_name(name: *const c_char, len: usize) -> u64 {
+    let name = slice::from_raw_parts(name as *const u8, len);
+    CURRENT.with(|current| match *current.borrow() {
+        Some(ref hash) => hash(name),
+        None => 0,
+    })
+}
@@ -0,0 +1,157 @@
+use super::{InterfaceBlock, ShaderVariable};
+
+use std::collections::HashMap;
+
+/// Translates names between the original shader and the translated one, in
+/// both directions.
+///
+/// Names are GL API names such as `lights[2].position` or `Block.field`:
+/// struct members are separated by dots, and interface block members are
+/// prefixed with the block name when the block has an instance name. Array
+/// subscripts are carried over unchanged, so `lights[2].position` maps to
+/// something like `_ulights[2]._uposition`. Members of shader I/O blocks
+/// are prefixed with the block name, as for transform feedback.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NameMap {
+    to_mapped: HashMap<String, String>,
+    to_original: HashMap<String, String>,
+    struct_to_mapped: HashMap<String, String>,
+    struct_to_original: HashMap<String, String>,
+}
+
+impl NameMap {
+    pub fn new() -> Self {
+        NameMap::default()
+    }
+
+    /// Adds `variables` and, recursively, their struct members.
+    pub fn add_variables(&mut self, variables: &[ShaderVariable]) {
+        for variable in variables {
+            self.add_variable("", "", variable);
+        }
+    }
+
+    /// Adds `blocks` and their members.
+    pub fn add_interface_blocks(&mut self, blocks: &[InterfaceBlock]) {
+        for block in blocks {
+            self.insert(block.name.clone(), block.mapped_name.clone());
+            let (prefix, mapped_prefix) = if block.instance_name.is_empty() {
+                (String::new(), String::new())
+            } else {
+                (
+                    format!("{}.", block.name),
+                    format!("{}.", block.mapped_name),
+                )
+            };
+            for field in &block.fields {
+                self.add_variable(&prefix, &mapped_prefix, field);
+            }
+        }
+    }
+
+    fn add_variable(&mut self, prefix: &str, mapped_prefix: &str, variable: &ShaderVariable) {
+        let name = format!("{}{}", prefix, variable.name);
+        let mapped_name = format!("{}{}", mapped_prefix, variable.mapped_name);
+        if !variable.mapped_struct_name.is_empty() {
+            self.struct_to_mapped.insert(
+                variable.struct_name.clone(),
+                variable.mapped_struct_name.clone(),
+            );
+            self.struct_to_original.insert(
+                variable.mapped_struct_name.clone(),
+                variable.struct_name.clone(),
+            );
+        }
+        if variable.is_struct() {
+            // Members of shader I/O blocks are named after the block, like
+            // those of interface blocks.
+            let (prefix, mapped_prefix) = if variable.is_shader_io_block {
+                (
+                    format!("{}.", variable.struct_name),
+                    format!("{}.", variable.mapped_struct_name),
+                )
+            } else {
+                (format!("{}.", name), format!("{}.", mapped_name))
+            };
+            for field in &variable.fields {
+                self.add_variable(&prefix, &mapped_prefix, field);
+            }
+        }
+        if !variable.name.is_empty() {
+            self.insert(name, mapped_name);
+        }
+    }
+
+    fn insert(&mut self, name: String, mapped_name: String) {
+        self.to_original.insert(mapped_name.clone(), name.clone());
+        self.to_mapped.insert(name, mapped_name);
+    }
+
+    /// Returns the name in the translated shader of `name`, for example to
+    /// pass it to `glBindAttribLocation` or `glGetUniformBlockIndex`.
+    pub fn mapped_name(&self, name: &str) -> Option<String> {
+        translate(&self.to_mapped, name)
+    }
+
+    /// Returns the name in the original shader of `mapped_name`, for example
+    /// to show the name of an active uniform to developers.
+    pub fn original_name(&self, mapped_name: &str) -> Option<String> {
+        translate(&self.to_original, mapped_name)
+    }
+
+    /// Returns the name in the translated shader of the type `name`.
+    ///
+    /// ANGLE only reports the translated names of shader I/O blocks, so
+    /// this is `None` for plain structs.
+    pub fn mapped_struct_name(&self, name: &str) -> Option<&str> {
+        self.struct_to_mapped.get(name).map(|s| &**s)
+    }
+
+    /// Returns the name in the original shader of the type `mapped_name`,
+    /// see `mapped_struct_name`.
+    pub fn original_struct_name(&self, mapped_name: &str) -> Option<&str> {
+        self.struct_to_original.get(mapped_name).map(|s| &**s)
+    }
+
+    /// Iterates over the pairs of original and mapped names, without array
+    /// subscripts and struct types.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.to_mapped.iter().map(|(k, v)| (&**k, &**v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.to_mapped.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_mapped.is_empty()
+    }
+}
+
+/// Looks up `name` with its array subscripts removed, then puts them back
+/// after the matching parts of the result.
+fn translate(map: &HashMap<String, String>, name: &str) -> Option<String> {
+    if !name.contains('[') {
+        return map.get(name).cloned();
+    }
+
+    let parts: Vec<_> = name
+        .split('.')
+        .map(|part| match part.find('[') {
+            Some(i) => part.split_at(i),
+            None => (part, ""),
+        })
+        .collect();
+    let bare: Vec<_> = parts.iter().map(|&(part, _)| part).collect();
+    let translated = map.get(&bare.join("."))?;
+    let translated: Vec<_> = translated.split('.').collect();
+    if translated.len() != parts.len() {
+        return None;
+    }
+    let with_subscripts: Vec<_> = translated
+        .iter()
+        .zip(&parts)
+        .map(|(part, &(_, subscripts))| format!("{}{}", part, subscripts))
+        .collect();
+    Some(with_subscripts.join("."))
+}
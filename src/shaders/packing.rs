@@ -0,0 +1,325 @@
+use super::ffi::{GLSLangCheckVariablesWithinPackingLimits, GLSLangGetTypePackingInfo};
+use super::variables::flatten;
+use super::ShaderVariable;
+
+use std::convert::TryFrom;
+
+/// Where a varying ended up in the grid of `max_vectors` rows of four
+/// components that `pack_varyings` fills.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PackedVarying {
+    /// The name of the varying, or of a member of a struct varying, for
+    /// example `light.color` or `lights[1].color`.
+    pub name: String,
+    /// The first row the varying occupies.
+    pub row: u32,
+    /// The first column the varying occupies.
+    pub column: u32,
+    /// The number of consecutive rows the varying occupies, including all of
+    /// its array elements.
+    pub rows: u32,
+    /// The number of consecutive columns the varying occupies in each row.
+    pub components: u32,
+}
+
+/// Packs `varyings` into `max_vectors` rows with the algorithm of the GLSL
+/// ES 1.00 spec, section A.7, and returns where each of them went.
+///
+/// Whether the varyings fit is decided by
+/// `sh::CheckVariablesWithinPackingLimits`. The rows and columns come from a
+/// port of its `VariablePacker`, which ANGLE doesn't expose. Counts above
+/// `i32::MAX` are clamped to it, and varyings with more rows than that don't
+/// fit. Only the varyings that are statically used should normally be passed
+/// in.
+pub fn pack_varyings(varyings: &[ShaderVariable], max_vectors: u32) -> Option<Vec<PackedVarying>> {
+    let max_vectors = i32::try_from(max_vectors).unwrap_or(i32::MAX);
+    if max_vectors == 0 {
+        // ANGLE asserts that there is at least one row.
+        return if varyings.is_empty() {
+            Some(Vec::new())
+        } else {
+            None
+        };
+    }
+
+    // This also keeps the row counts below within an i32.
+    if varyings
+        .iter()
+        .any(|varying| exceeds_rows(varying, 1, max_vectors))
+    {
+        return None;
+    }
+
+    let mut flat = Vec::new();
+    flatten(varyings, &mut flat);
+    let fits =
+        unsafe { GLSLangCheckVariablesWithinPackingLimits(max_vectors, flat.as_ptr(), flat.len()) };
+    if fits == 0 {
+        return None;
+    }
+
+    let mut expanded = Vec::new();
+    for varying in varyings {
+        expand(varying, varying.name.clone(), &mut expanded);
+    }
+    let packed = VaryingPacker::new(max_vectors).pack(expanded);
+    debug_assert!(
+        packed.is_some(),
+        "VariablePacker port rejected varyings that ANGLE packs"
+    );
+    packed
+}
+
+/// Whether `variable`, with `elements` enclosing array elements, needs more
+/// than `max_rows` rows on its own.
+fn exceeds_rows(variable: &ShaderVariable, elements: u64, max_rows: i32) -> bool {
+    let elements = elements.saturating_mul(u64::from(variable.array_size_product()));
+    if variable.is_struct() {
+        return variable
+            .fields
+            .iter()
+            .any(|field| exceeds_rows(field, elements, max_rows));
+    }
+    let (_, rows, _) = packing_info(variable);
+    elements.saturating_mul(rows as u64) > max_rows as u64
+}
+
+/// The components per row, rows and sort order of a non-struct variable,
+/// see `GetVariablePackingRows` and `GetTypePackingInfo`.
+fn packing_info(variable: &ShaderVariable) -> (i32, i32, i32) {
+    let (mut components, mut rows, mut sort_order) = (0, 0, 0);
+    unsafe { GLSLangGetTypePackingInfo(variable.ty, &mut components, &mut rows, &mut sort_order) }
+    (components, rows, sort_order)
+}
+
+struct ExpandedVarying {
+    name: String,
+    components: i32,
+    rows: i32,
+    sort_order: i32,
+    array_size: i32,
+}
+
+/// Splits struct varyings into their members, see `ExpandVariable`.
+fn expand(variable: &ShaderVariable, name: String, expanded: &mut Vec<ExpandedVarying>) {
+    if !variable.is_struct() {
+        let (components, rows, sort_order) = packing_info(variable);
+        expanded.push(ExpandedVarying {
+            name,
+            components,
+            rows,
+            sort_order,
+            // `exceeds_rows` has ruled larger arrays out.
+            array_size: i32::try_from(variable.array_size_product()).unwrap_or(i32::MAX),
+        });
+        return;
+    }
+
+    // Arrays of structs are expanded element by element, outermost
+    // dimension first.
+    let mut element_names = vec![name];
+    for &size in variable.array_sizes.iter().rev() {
+        element_names = element_names
+            .iter()
+            .flat_map(|name| (0..size).map(move |i| format!("{}[{}]", name, i)))
+            .collect();
+    }
+    for element_name in element_names {
+        for field in &variable.fields {
+            expand(field, format!("{}.{}", element_name, field.name), expanded);
+        }
+    }
+}
+
+const NUM_COLUMNS: i32 = 4;
+const COLUMN_MASK: u32 = (1 << NUM_COLUMNS) - 1;
+
+/// A port of ANGLE's `VariablePacker` that records where each varying goes.
+struct VaryingPacker {
+    top_non_full_row: i32,
+    bottom_non_full_row: i32,
+    max_rows: i32,
+    rows: Vec<u32>,
+}
+
+impl VaryingPacker {
+    fn new(max_rows: i32) -> Self {
+        VaryingPacker {
+            top_non_full_row: 0,
+            bottom_non_full_row: max_rows - 1,
+            max_rows,
+            rows: vec![0; max_rows.max(0) as usize],
+        }
+    }
+
+    fn column_flags(column: i32, components: i32) -> u32 {
+        ((COLUMN_MASK << (NUM_COLUMNS - components)) & COLUMN_MASK) >> column
+    }
+
+    fn fill_columns(&mut self, top_row: i32, num_rows: i32, column: i32, components: i32) {
+        let flags = Self::column_flags(column, components);
+        for row in top_row..top_row + num_rows {
+            self.rows[row as usize] |= flags;
+        }
+    }
+
+    /// Finds the smallest run of free rows in `column` that fits `num_rows`,
+    /// returning its top row and size.
+    fn search_column(&mut self, column: i32, num_rows: i32) -> Option<(i32, i32)> {
+        while self.top_non_full_row < self.max_rows
+            && self.rows[self.top_non_full_row as usize] == COLUMN_MASK
+        {
+            self.top_non_full_row += 1;
+        }
+        while self.bottom_non_full_row >= 0
+            && self.rows[self.bottom_non_full_row as usize] == COLUMN_MASK
+        {
+            self.bottom_non_full_row -= 1;
+        }
+        if self.bottom_non_full_row - self.top_non_full_row + 1 < num_rows {
+            return None;
+        }
+
+        let flags = Self::column_flags(column, 1);
+        let mut top_good_row = 0;
+        let mut smallest_good_top = -1;
+        let mut smallest_good_size = self.max_rows + 1;
+        let bottom_row = self.bottom_non_full_row + 1;
+        let mut found = false;
+        for row in self.top_non_full_row..=bottom_row {
+            let row_empty = row < bottom_row && self.rows[row as usize] & flags == 0;
+            if row_empty {
+                if !found {
+                    top_good_row = row;
+                    found = true;
+                }
+            } else {
+                if found {
+                    let size = row - top_good_row;
+                    if size >= num_rows && size < smallest_good_size {
+                        smallest_good_size = size;
+                        smallest_good_top = top_good_row;
+                    }
+                }
+                found = false;
+            }
+        }
+        if smallest_good_top < 0 {
+            return None;
+        }
+        Some((smallest_good_top, smallest_good_size))
+    }
+
+    fn pack(mut self, mut varyings: Vec<ExpandedVarying>) -> Option<Vec<PackedVarying>> {
+        if self.max_rows <= 0 {
+            return if varyings.is_empty() {
+                Some(Vec::new())
+            } else {
+                None
+            };
+        }
+        if varyings
+            .iter()
+            .any(|v| v.array_size > self.max_rows / v.rows)
+        {
+            return None;
+        }
+
+        varyings.sort_by(|a, b| {
+            a.sort_order
+                .cmp(&b.sort_order)
+                .then(b.array_size.cmp(&a.array_size))
+        });
+
+        let mut packed = Vec::with_capacity(varyings.len());
+        let mut place = |varying: &ExpandedVarying, row: i32, column: i32| {
+            packed.push(PackedVarying {
+                name: varying.name.clone(),
+                row: row as u32,
+                column: column as u32,
+                rows: (varying.rows * varying.array_size) as u32,
+                components: varying.components as u32,
+            })
+        };
+        let mut remaining = varyings.iter().peekable();
+
+        // Four column varyings take whole rows from the top.
+        while let Some(varying) = remaining.next_if(|v| v.components == 4) {
+            place(varying, self.top_non_full_row, 0);
+            self.top_non_full_row += varying.rows * varying.array_size;
+            if self.top_non_full_row > self.max_rows {
+                return None;
+            }
+        }
+
+        // Three column varyings take the first three columns of the rows
+        // below.
+        let mut num_3_column_rows = 0;
+        while let Some(varying) = remaining.next_if(|v| v.components == 3) {
+            place(varying, self.top_non_full_row + num_3_column_rows, 0);
+            num_3_column_rows += varying.rows * varying.array_size;
+            if self.top_non_full_row + num_3_column_rows > self.max_rows {
+                return None;
+            }
+        }
+        self.fill_columns(self.top_non_full_row, num_3_column_rows, 0, 3);
+
+        // Two column varyings fill columns 0-1 downwards from there, then
+        // columns 2-3 upwards from the bottom.
+        let top_2_column_row = self.top_non_full_row + num_3_column_rows;
+        let two_column_rows_available = self.max_rows - top_2_column_row;
+        let mut rows_available_01 = two_column_rows_available;
+        let mut rows_available_23 = two_column_rows_available;
+        let mut in_columns_23 = Vec::new();
+        while let Some(varying) = remaining.next_if(|v| v.components == 2) {
+            let num_rows = varying.rows * varying.array_size;
+            if num_rows <= rows_available_01 {
+                place(
+                    varying,
+                    top_2_column_row + two_column_rows_available - rows_available_01,
+                    0,
+                );
+                rows_available_01 -= num_rows;
+            } else if num_rows <= rows_available_23 {
+                in_columns_23.push(varying);
+                rows_available_23 -= num_rows;
+            } else {
+                return None;
+            }
+        }
+        let rows_used_01 = two_column_rows_available - rows_available_01;
+        let rows_used_23 = two_column_rows_available - rows_available_23;
+        let mut row = self.max_rows - rows_used_23;
+        for varying in in_columns_23 {
+            place(varying, row, 2);
+            row += varying.rows * varying.array_size;
+        }
+        self.fill_columns(top_2_column_row, rows_used_01, 0, 2);
+        self.fill_columns(self.max_rows - rows_used_23, rows_used_23, 2, 2);
+
+        // Single column varyings go in the column with the smallest run of
+        // free rows that fits them.
+        for varying in remaining {
+            let num_rows = varying.rows * varying.array_size;
+            let mut smallest_column = -1;
+            let mut smallest_size = self.max_rows + 1;
+            let mut top_row = -1;
+            for column in 0..NUM_COLUMNS {
+                if let Some((row, size)) = self.search_column(column, num_rows) {
+                    if size < smallest_size {
+                        smallest_size = size;
+                        smallest_column = column;
+                        top_row = row;
+                    }
+                }
+            }
+            if smallest_column < 0 {
+                return None;
+            }
+            place(varying, top_row, smallest_column);
+            self.fill_columns(top_row, num_rows, smallest_column, 1);
+        }
+
+        Some(packed)
+    }
+}
@@ -0,0 +1,448 @@
+use super::ffi::{GLSLangMacroDefinition, GLSLangPreprocess};
+use super::{AsAngleEnum, Diagnostic, Severity, ShaderSpec, SourceLocation};
+
+use std::error;
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+/// The kinds of directives reported by `GLSLangPreprocess`.
+const DIRECTIVE_VERSION: c_int = 0;
+const DIRECTIVE_EXTENSION: c_int = 1;
+const DIRECTIVE_PRAGMA: c_int = 2;
+const DIRECTIVE_ERROR: c_int = 3;
+
+/// The macros every shader starts with.
+const STANDARD_MACROS: &[&str] = &["__LINE__", "__FILE__", "__VERSION__", "GL_ES"];
+
+/// The `angle::pp::Token` flags.
+const AT_START_OF_LINE: c_uint = 1 << 0;
+const HAS_LEADING_SPACE: c_uint = 1 << 1;
+
+/// The types of `angle::pp::Token` reported after preprocessing.
+const IDENTIFIER: c_int = 258;
+const CONST_INT: c_int = 259;
+const CONST_FLOAT: c_int = 260;
+
+/// ANGLE's GLSL preprocessor, on its own.
+#[derive(Clone, Debug)]
+pub struct Preprocessor {
+    spec: ShaderSpec,
+    macros: Vec<Definition>,
+}
+
+/// A macro given to `Preprocessor::define`.
+#[derive(Clone, Debug)]
+struct Definition {
+    name: String,
+    /// The comma separated parameters of function-like macros.
+    parameters: Option<String>,
+    replacement: String,
+}
+
+impl Preprocessor {
+    /// A preprocessor following the rules of `spec`, with the standard
+    /// `__LINE__`, `__FILE__`, `__VERSION__` and `GL_ES` macros.
+    pub fn new(spec: ShaderSpec) -> Self {
+        Preprocessor {
+            spec,
+            macros: Vec::new(),
+        }
+    }
+
+    /// Predefines a macro, like `#define` followed by `name` and
+    /// `replacement` would, but without the restrictions on reserved names,
+    /// so that for example the macros the compiler defines for extensions
+    /// can be given. `name` is either an identifier or, for function-like
+    /// macros, an identifier followed by its parameters, as in
+    /// `MUL(a, b)`.
+    ///
+    /// Defining a macro again replaces it. Unlike the standard macros, the
+    /// shader can `#undef` it.
+    pub fn define(&mut self, name: &str, replacement: &str) -> Result<&mut Self, MacroError> {
+        let error = |reason| MacroError {
+            name: name.to_owned(),
+            reason,
+        };
+        let (identifier, parameters) = parse_macro_name(name).map_err(error)?;
+        if STANDARD_MACROS.contains(&identifier) {
+            return Err(error("the standard macros can't be redefined"));
+        }
+        if replacement.contains(['\n', '\r', '\0']) {
+            return Err(error("the replacement must be a single line"));
+        }
+        if replacement.contains("/*") || replacement.contains("//") {
+            return Err(error("the replacement can't have comments"));
+        }
+
+        self.macros
+            .retain(|definition| definition.name != identifier);
+        self.macros.push(Definition {
+            name: identifier.to_owned(),
+            parameters: parameters.map(|parameters| parameters.join(",")),
+            replacement: replacement.to_owned(),
+        });
+        Ok(self)
+    }
+
+    /// Preprocesses `strings`, which are concatenated like the source strings
+    /// of `ShaderValidator::compile`.
+    pub fn preprocess(&self, strings: &[&str]) -> PreprocessedSource {
+        let mut output = PreprocessedSource {
+            tokens: Vec::new(),
+            macros: Vec::new(),
+            directives: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+
+        let pointers: Vec<_> = strings
+            .iter()
+            .map(|s| s.as_ptr() as *const c_char)
+            .collect();
+        let lengths: Vec<_> = strings.iter().map(|s| s.len() as c_int).collect();
+        let macros: Vec<_> = self
+            .macros
+            .iter()
+            .map(|definition| {
+                let parameters = definition.parameters.as_ref().map_or("", |p| &**p);
+                GLSLangMacroDefinition {
+                    name: definition.name.as_ptr() as *const c_char,
+                    nameLength: definition.name.len(),
+                    function: definition.parameters.is_some() as c_int,
+                    parameters: parameters.as_ptr() as *const c_char,
+                    parametersLength: parameters.len(),
+                    replacement: definition.replacement.as_ptr() as *const c_char,
+                    replacementLength: definition.replacement.len(),
+                }
+            })
+            .collect();
+        let success = unsafe {
+            GLSLangPreprocess(
+                self.spec.as_angle_enum(),
+                pointers.as_ptr(),
+                lengths.as_ptr(),
+                strings.len(),
+                macros.as_ptr(),
+                macros.len(),
+                Some(each_token),
+                Some(each_directive),
+                Some(each_diagnostic),
+                Some(each_macro),
+                &mut output as *mut PreprocessedSource as *mut c_void,
+            )
+        };
+        if success == 0 && !output.has_errors() {
+            // The tokenizer rejected the input before reporting anything.
+            output.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                source_string: None,
+                source_name: None,
+                line: None,
+                token: None,
+                message: "Couldn't initialize the preprocessor".to_owned(),
+            });
+        }
+        output
+    }
+}
+
+/// Why `Preprocessor::define` rejected a macro.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MacroError {
+    /// The name given to `define`.
+    pub name: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid macro {}: {}", self.name, self.reason)
+    }
+}
+
+impl error::Error for MacroError {}
+
+/// Splits the name given to `Preprocessor::define` into the identifier and,
+/// for function-like macros, the parameters.
+fn parse_macro_name(name: &str) -> Result<(&str, Option<Vec<&str>>), &'static str> {
+    let end = name.find('(').unwrap_or(name.len());
+    let identifier = &name[..end];
+    if !is_identifier(identifier) {
+        return Err("the name must be an identifier");
+    }
+    if end == name.len() {
+        return Ok((identifier, None));
+    }
+
+    let parameters = name[end + 1..]
+        .strip_suffix(')')
+        .ok_or("the parameters must end with ')'")?;
+    if parameters.trim().is_empty() {
+        return Ok((identifier, Some(Vec::new())));
+    }
+    let parameters: Vec<_> = parameters.split(',').map(str::trim).collect();
+    for (i, parameter) in parameters.iter().enumerate() {
+        if !is_identifier(parameter) {
+            return Err("the parameters must be identifiers");
+        }
+        if parameters[..i].contains(parameter) {
+            return Err("the parameters must have different names");
+        }
+    }
+    Ok((identifier, Some(parameters)))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// The result of `Preprocessor::preprocess`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessedSource {
+    /// The tokens left after expanding macros and removing directives and
+    /// skipped conditional blocks.
+    pub tokens: Vec<Token>,
+    /// Every macro defined at the end of the source, including predefined
+    /// ones, sorted by name.
+    pub macros: Vec<Macro>,
+    /// The `#version`, `#extension` and `#pragma` directives, in order.
+    pub directives: Vec<Directive>,
+    /// The preprocessor's errors and warnings, including `#error`
+    /// directives.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl PreprocessedSource {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Returns the tokens as text, with the tokens of each line on a line
+    /// of their own and as many blank lines in between as in the source.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        let mut previous: Option<SourceLocation> = None;
+        for token in &self.tokens {
+            match previous {
+                Some(previous) if token.location != previous || token.at_start_of_line => {
+                    let newlines = if token.location.string == previous.string
+                        && token.location.line > previous.line
+                    {
+                        token.location.line - previous.line
+                    } else {
+                        1
+                    };
+                    for _ in 0..newlines {
+                        text.push('\n');
+                    }
+                }
+                Some(_) if token.has_leading_space => text.push(' '),
+                _ => {}
+            }
+            previous = Some(token.location);
+            text.push_str(&token.text);
+        }
+        text
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Identifier,
+    IntConstant,
+    FloatConstant,
+    /// An operator or punctuation, such as `+=` or `;`.
+    Operator,
+}
+
+/// A token produced by the preprocessor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Where the token, or the macro invocation it comes from, is.
+    pub location: SourceLocation,
+    pub at_start_of_line: bool,
+    pub has_leading_space: bool,
+}
+
+/// A macro defined with `#define` or predefined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Macro {
+    pub name: String,
+    /// The parameters of function-like macros, `None` for object-like ones.
+    pub parameters: Option<Vec<String>>,
+    /// The replacement list, with tokens separated by single spaces.
+    pub replacement: String,
+    /// Whether the macro is one of the standard macros or was given to
+    /// `Preprocessor::define`.
+    pub predefined: bool,
+}
+
+/// A directive that the preprocessor passes on to the compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Directive {
+    /// `#version 300 es`.
+    Version {
+        location: SourceLocation,
+        version: i32,
+    },
+    /// `#extension name : behavior`.
+    Extension {
+        location: SourceLocation,
+        name: String,
+        behavior: String,
+    },
+    /// `#pragma name(value)`, or `#pragma STDGL name(value)` when `stdgl` is
+    /// set.
+    Pragma {
+        location: SourceLocation,
+        name: String,
+        value: String,
+        stdgl: bool,
+    },
+}
+
+fn location(file: c_int, line: c_int) -> SourceLocation {
+    SourceLocation {
+        string: file as u32,
+        line: line as u32,
+    }
+}
+
+unsafe fn output<'a>(closure: *mut c_void) -> &'a mut PreprocessedSource {
+    &mut *(closure as *mut PreprocessedSource)
+}
+
+unsafe extern "C" fn each_token(
+    closure: *mut c_void,
+    ty: c_int,
+    text: *const c_char,
+    text_len: usize,
+    file: c_int,
+    line: c_int,
+    flags: c_uint,
+) {
+    let kind = match ty {
+        IDENTIFIER => TokenKind::Identifier,
+        CONST_INT => TokenKind::IntConstant,
+        CONST_FLOAT => TokenKind::FloatConstant,
+        _ => TokenKind::Operator,
+    };
+    output(closure).tokens.push(Token {
+        kind,
+        text: super::to_string(text, text_len),
+        location: location(file, line),
+        at_start_of_line: flags & AT_START_OF_LINE != 0,
+        has_leading_space: flags & HAS_LEADING_SPACE != 0,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn each_directive(
+    closure: *mut c_void,
+    kind: c_int,
+    file: c_int,
+    line: c_int,
+    name: *const c_char,
+    name_len: usize,
+    value: *const c_char,
+    value_len: usize,
+    number: c_int,
+) {
+    let output = output(closure);
+    let location = location(file, line);
+    let name = super::to_string(name, name_len);
+    let value = super::to_string(value, value_len);
+    let directive = match kind {
+        DIRECTIVE_VERSION => Directive::Version {
+            location,
+            version: number,
+        },
+        DIRECTIVE_EXTENSION => Directive::Extension {
+            location,
+            name,
+            behavior: value,
+        },
+        DIRECTIVE_PRAGMA => Directive::Pragma {
+            location,
+            name,
+            value,
+            stdgl: number != 0,
+        },
+        DIRECTIVE_ERROR => {
+            output.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                source_string: Some(location.string),
+                source_name: None,
+                line: Some(location.line),
+                token: None,
+                message: value.trim_start().to_owned(),
+            });
+            return;
+        }
+        _ => return,
+    };
+    output.directives.push(directive);
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn each_diagnostic(
+    closure: *mut c_void,
+    error: c_int,
+    file: c_int,
+    line: c_int,
+    message: *const c_char,
+    message_len: usize,
+    text: *const c_char,
+    text_len: usize,
+) {
+    let text = super::to_string(text, text_len);
+    output(closure).diagnostics.push(Diagnostic {
+        severity: if error != 0 {
+            Severity::Error
+        } else {
+            Severity::Warning
+        },
+        source_string: Some(file as u32),
+        source_name: None,
+        line: Some(line as u32),
+        token: if text.is_empty() { None } else { Some(text) },
+        message: super::to_string(message, message_len),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn each_macro(
+    closure: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    predefined: c_int,
+    function: c_int,
+    parameters: *const c_char,
+    parameters_len: usize,
+    replacement: *const c_char,
+    replacement_len: usize,
+) {
+    let parameters = super::to_string(parameters, parameters_len);
+    let parameters = if function == 0 {
+        None
+    } else if parameters.is_empty() {
+        Some(Vec::new())
+    } else {
+        Some(parameters.split(',').map(String::from).collect())
+    };
+    output(closure).macros.push(Macro {
+        name: super::to_string(name, name_len),
+        parameters,
+        replacement: super::to_string(replacement, replacement_len),
+        predefined: predefined != 0,
+    });
+}
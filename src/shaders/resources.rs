@@ -0,0 +1,384 @@
+use super::ffi::{
+    GLSLangSetMaxComputeWorkGroupCount, GLSLangSetMaxComputeWorkGroupSize, ShBuiltInResources,
+};
+use super::BuiltInResources;
+
+use std::os::raw::c_int;
+
+macro_rules! extensions {
+    ($($variant:ident => $field:ident;)*) => {
+        /// An extension that can be exposed to shaders through `BuiltInResources`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Extension {
+            $($variant,)*
+        }
+
+        impl Extension {
+            /// Every extension, in `ShBuiltInResources` order.
+            pub const ALL: &'static [Extension] = &[$(Extension::$variant,)*];
+
+            /// The extension name without the `GL_` prefix, for example
+            /// `OES_standard_derivatives`.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(Extension::$variant => stringify!($field),)*
+                }
+            }
+
+            fn field(self, resources: &mut ShBuiltInResources) -> &mut c_int {
+                match self {
+                    $(Extension::$variant => &mut resources.$field,)*
+                }
+            }
+        }
+    };
+}
+
+extensions! {
+    OesStandardDerivatives => OES_standard_derivatives;
+    OesEglImageExternal => OES_EGL_image_external;
+    OesEglImageExternalEssl3 => OES_EGL_image_external_essl3;
+    NvEglStreamConsumerExternal => NV_EGL_stream_consumer_external;
+    ArbTextureRectangle => ARB_texture_rectangle;
+    ExtBlendFuncExtended => EXT_blend_func_extended;
+    ExtDrawBuffers => EXT_draw_buffers;
+    ExtFragDepth => EXT_frag_depth;
+    ExtShaderTextureLod => EXT_shader_texture_lod;
+    ExtShaderFramebufferFetch => EXT_shader_framebuffer_fetch;
+    ExtShaderFramebufferFetchNonCoherent => EXT_shader_framebuffer_fetch_non_coherent;
+    NvShaderFramebufferFetch => NV_shader_framebuffer_fetch;
+    NvShaderNoperspectiveInterpolation => NV_shader_noperspective_interpolation;
+    ArmShaderFramebufferFetch => ARM_shader_framebuffer_fetch;
+    OvrMultiview => OVR_multiview;
+    OvrMultiview2 => OVR_multiview2;
+    ExtMultisampledRenderToTexture => EXT_multisampled_render_to_texture;
+    ExtMultisampledRenderToTexture2 => EXT_multisampled_render_to_texture2;
+    ExtYuvTarget => EXT_YUV_target;
+    ExtGeometryShader => EXT_geometry_shader;
+    OesGeometryShader => OES_geometry_shader;
+    OesShaderIoBlocks => OES_shader_io_blocks;
+    ExtShaderIoBlocks => EXT_shader_io_blocks;
+    ExtGpuShader5 => EXT_gpu_shader5;
+    ExtShaderNonConstantGlobalInitializers => EXT_shader_non_constant_global_initializers;
+    OesTextureStorageMultisample2DArray => OES_texture_storage_multisample_2d_array;
+    OesTexture3D => OES_texture_3D;
+    AngleShaderPixelLocalStorage => ANGLE_shader_pixel_local_storage;
+    AngleTextureMultisample => ANGLE_texture_multisample;
+    AngleMultiDraw => ANGLE_multi_draw;
+    AngleBaseVertexBaseInstance => ANGLE_base_vertex_base_instance;
+    WebGLVideoTexture => WEBGL_video_texture;
+    AppleClipDistance => APPLE_clip_distance;
+    OesTextureCubeMapArray => OES_texture_cube_map_array;
+    ExtTextureCubeMapArray => EXT_texture_cube_map_array;
+    ExtShadowSamplers => EXT_shadow_samplers;
+    OesShaderMultisampleInterpolation => OES_shader_multisample_interpolation;
+    OesShaderImageAtomic => OES_shader_image_atomic;
+    ExtTessellationShader => EXT_tessellation_shader;
+    OesTextureBuffer => OES_texture_buffer;
+    ExtTextureBuffer => EXT_texture_buffer;
+    OesSampleVariables => OES_sample_variables;
+    ExtClipCullDistance => EXT_clip_cull_distance;
+    ExtPrimitiveBoundingBox => EXT_primitive_bounding_box;
+    OesPrimitiveBoundingBox => OES_primitive_bounding_box;
+    AngleBaseVertexBaseInstanceShaderBuiltin => ANGLE_base_vertex_base_instance_shader_builtin;
+    AndroidExtensionPackEs31a => ANDROID_extension_pack_es31a;
+    KhrBlendEquationAdvanced => KHR_blend_equation_advanced;
+    NvDrawBuffers => NV_draw_buffers;
+}
+
+impl Extension {
+    /// Looks an extension up by its GL or WebGL name, with or without the
+    /// `GL_` prefix.
+    pub fn from_name(name: &str) -> Option<Extension> {
+        let name = name.strip_prefix("GL_").unwrap_or(name);
+        let name = match name {
+            "WEBGL_draw_buffers" => "EXT_draw_buffers",
+            "WEBGL_multi_draw" => "ANGLE_multi_draw",
+            "WEBGL_draw_instanced_base_vertex_base_instance" => {
+                "ANGLE_base_vertex_base_instance_shader_builtin"
+            }
+            "WEBGL_blend_func_extended" => "EXT_blend_func_extended",
+            "WEBGL_clip_cull_distance" => "EXT_clip_cull_distance",
+            "WEBGL_shader_pixel_local_storage" => "ANGLE_shader_pixel_local_storage",
+            name => name,
+        };
+        Extension::ALL.iter().cloned().find(|e| e.name() == name)
+    }
+}
+
+macro_rules! limits {
+    ($($variant:ident => $field:ident, $pname:expr, $divisor:expr;)*) => {
+        /// An integer limit that shaders observe as a built-in constant.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Limit {
+            $($variant,)*
+        }
+
+        impl Limit {
+            /// Every limit, in `ShBuiltInResources` order.
+            pub const ALL: &'static [Limit] = &[$(Limit::$variant,)*];
+
+            /// The `glGetIntegerv` parameter this limit is read from, if any.
+            pub fn gl_enum(&self) -> Option<u32> {
+                match *self {
+                    $(Limit::$variant => $pname,)*
+                }
+            }
+
+            /// ANGLE counts some limits in vectors where GL reports components.
+            fn gl_divisor(self) -> c_int {
+                match self {
+                    $(Limit::$variant => $divisor,)*
+                }
+            }
+
+            fn field(self, resources: &mut ShBuiltInResources) -> &mut c_int {
+                match self {
+                    $(Limit::$variant => &mut resources.$field,)*
+                }
+            }
+        }
+    };
+}
+
+limits! {
+    MaxVertexAttribs => MaxVertexAttribs, Some(0x8869), 1;
+    MaxVertexUniformVectors => MaxVertexUniformVectors, Some(0x8DFB), 1;
+    MaxVaryingVectors => MaxVaryingVectors, Some(0x8DFC), 1;
+    MaxVertexTextureImageUnits => MaxVertexTextureImageUnits, Some(0x8B4C), 1;
+    MaxCombinedTextureImageUnits => MaxCombinedTextureImageUnits, Some(0x8B4D), 1;
+    MaxTextureImageUnits => MaxTextureImageUnits, Some(0x8872), 1;
+    MaxFragmentUniformVectors => MaxFragmentUniformVectors, Some(0x8DFD), 1;
+    MaxDrawBuffers => MaxDrawBuffers, Some(0x8824), 1;
+    MaxVertexOutputVectors => MaxVertexOutputVectors, Some(0x9122), 4;
+    MaxFragmentInputVectors => MaxFragmentInputVectors, Some(0x9125), 4;
+    MinProgramTexelOffset => MinProgramTexelOffset, Some(0x8904), 1;
+    MaxProgramTexelOffset => MaxProgramTexelOffset, Some(0x8905), 1;
+    MaxFragmentUniformBlocks => MaxFragmentUniformBlocks, Some(0x8A2D), 1;
+    MaxVertexUniformBlocks => MaxVertexUniformBlocks, Some(0x8A2B), 1;
+    MaxDualSourceDrawBuffers => MaxDualSourceDrawBuffers, Some(0x88FC), 1;
+    MaxViewsOVR => MaxViewsOVR, Some(0x9631), 1;
+    MaxExpressionComplexity => MaxExpressionComplexity, None, 1;
+    MaxCallStackDepth => MaxCallStackDepth, None, 1;
+    MaxFunctionParameters => MaxFunctionParameters, None, 1;
+    MinProgramTextureGatherOffset => MinProgramTextureGatherOffset, Some(0x8E5E), 1;
+    MaxProgramTextureGatherOffset => MaxProgramTextureGatherOffset, Some(0x8E5F), 1;
+    MaxImageUnits => MaxImageUnits, Some(0x8F38), 1;
+    MaxSamples => MaxSamples, Some(0x8D57), 1;
+    MaxVertexImageUniforms => MaxVertexImageUniforms, Some(0x90CA), 1;
+    MaxFragmentImageUniforms => MaxFragmentImageUniforms, Some(0x90CE), 1;
+    MaxComputeImageUniforms => MaxComputeImageUniforms, Some(0x91BD), 1;
+    MaxCombinedImageUniforms => MaxCombinedImageUniforms, Some(0x90CF), 1;
+    MaxUniformLocations => MaxUniformLocations, Some(0x826E), 1;
+    MaxCombinedShaderOutputResources => MaxCombinedShaderOutputResources, Some(0x8F39), 1;
+    MaxComputeUniformComponents => MaxComputeUniformComponents, Some(0x8263), 1;
+    MaxComputeTextureImageUnits => MaxComputeTextureImageUnits, Some(0x91BC), 1;
+    MaxComputeAtomicCounters => MaxComputeAtomicCounters, Some(0x8265), 1;
+    MaxComputeAtomicCounterBuffers => MaxComputeAtomicCounterBuffers, Some(0x8264), 1;
+    MaxVertexAtomicCounters => MaxVertexAtomicCounters, Some(0x92D2), 1;
+    MaxFragmentAtomicCounters => MaxFragmentAtomicCounters, Some(0x92D6), 1;
+    MaxCombinedAtomicCounters => MaxCombinedAtomicCounters, Some(0x92D7), 1;
+    MaxAtomicCounterBindings => MaxAtomicCounterBindings, Some(0x92DC), 1;
+    MaxVertexAtomicCounterBuffers => MaxVertexAtomicCounterBuffers, Some(0x92CC), 1;
+    MaxFragmentAtomicCounterBuffers => MaxFragmentAtomicCounterBuffers, Some(0x92D0), 1;
+    MaxCombinedAtomicCounterBuffers => MaxCombinedAtomicCounterBuffers, Some(0x92D1), 1;
+    MaxAtomicCounterBufferSize => MaxAtomicCounterBufferSize, Some(0x92D8), 1;
+    MaxUniformBufferBindings => MaxUniformBufferBindings, Some(0x8A2F), 1;
+    MaxShaderStorageBufferBindings => MaxShaderStorageBufferBindings, Some(0x90DD), 1;
+    MaxComputeUniformBlocks => MaxComputeUniformBlocks, Some(0x91BB), 1;
+    MaxGeometryUniformComponents => MaxGeometryUniformComponents, Some(0x8DDF), 1;
+    MaxGeometryUniformBlocks => MaxGeometryUniformBlocks, Some(0x8A2C), 1;
+    MaxGeometryInputComponents => MaxGeometryInputComponents, Some(0x9123), 1;
+    MaxGeometryOutputComponents => MaxGeometryOutputComponents, Some(0x9124), 1;
+    MaxGeometryOutputVertices => MaxGeometryOutputVertices, Some(0x8DE0), 1;
+    MaxGeometryTotalOutputComponents => MaxGeometryTotalOutputComponents, Some(0x8DE1), 1;
+    MaxGeometryTextureImageUnits => MaxGeometryTextureImageUnits, Some(0x8C29), 1;
+    MaxGeometryAtomicCounterBuffers => MaxGeometryAtomicCounterBuffers, Some(0x92CF), 1;
+    MaxGeometryAtomicCounters => MaxGeometryAtomicCounters, Some(0x92D5), 1;
+    MaxGeometryShaderStorageBlocks => MaxGeometryShaderStorageBlocks, Some(0x90D7), 1;
+    MaxGeometryShaderInvocations => MaxGeometryShaderInvocations, Some(0x8E5A), 1;
+    MaxGeometryImageUniforms => MaxGeometryImageUniforms, Some(0x90CD), 1;
+    MaxTessControlInputComponents => MaxTessControlInputComponents, Some(0x886C), 1;
+    MaxTessControlOutputComponents => MaxTessControlOutputComponents, Some(0x8E83), 1;
+    MaxTessControlTextureImageUnits => MaxTessControlTextureImageUnits, Some(0x8E81), 1;
+    MaxTessControlUniformComponents => MaxTessControlUniformComponents, Some(0x8E7F), 1;
+    MaxTessControlTotalOutputComponents => MaxTessControlTotalOutputComponents, Some(0x8E85), 1;
+    MaxTessControlImageUniforms => MaxTessControlImageUniforms, Some(0x90CB), 1;
+    MaxTessControlAtomicCounters => MaxTessControlAtomicCounters, Some(0x92D3), 1;
+    MaxTessControlAtomicCounterBuffers => MaxTessControlAtomicCounterBuffers, Some(0x92CD), 1;
+    MaxTessControlUniformBlocks => MaxTessControlUniformBlocks, Some(0x8E89), 1;
+    MaxTessPatchComponents => MaxTessPatchComponents, Some(0x8E84), 1;
+    MaxPatchVertices => MaxPatchVertices, Some(0x8E7D), 1;
+    MaxTessGenLevel => MaxTessGenLevel, Some(0x8E7E), 1;
+    MaxTessEvaluationInputComponents => MaxTessEvaluationInputComponents, Some(0x886D), 1;
+    MaxTessEvaluationOutputComponents => MaxTessEvaluationOutputComponents, Some(0x8E86), 1;
+    MaxTessEvaluationTextureImageUnits => MaxTessEvaluationTextureImageUnits, Some(0x8E82), 1;
+    MaxTessEvaluationUniformComponents => MaxTessEvaluationUniformComponents, Some(0x8E80), 1;
+    MaxTessEvaluationImageUniforms => MaxTessEvaluationImageUniforms, Some(0x90CC), 1;
+    MaxTessEvaluationAtomicCounters => MaxTessEvaluationAtomicCounters, Some(0x92D4), 1;
+    MaxTessEvaluationAtomicCounterBuffers => MaxTessEvaluationAtomicCounterBuffers, Some(0x92CE), 1;
+    MaxTessEvaluationUniformBlocks => MaxTessEvaluationUniformBlocks, Some(0x8E8A), 1;
+    SubPixelBits => SubPixelBits, Some(0x0D50), 1;
+    MaxClipDistances => MaxClipDistances, Some(0x0D32), 1;
+    MaxCullDistances => MaxCullDistances, Some(0x82F9), 1;
+    MaxCombinedClipAndCullDistances => MaxCombinedClipAndCullDistances, Some(0x82FA), 1;
+    MaxPixelLocalStoragePlanes => MaxPixelLocalStoragePlanes, Some(0x96E0), 1;
+    MaxColorAttachmentsWithActivePixelLocalStorage => MaxColorAttachmentsWithActivePixelLocalStorage, Some(0x96E1), 1;
+    MaxCombinedDrawBuffersAndPixelLocalStoragePlanes => MaxCombinedDrawBuffersAndPixelLocalStoragePlanes, Some(0x96E2), 1;
+}
+
+/// Builds the `BuiltInResources` a `ShaderValidator` is constructed with.
+///
+/// Starts from ANGLE's defaults, which are the GLES 2.0 minimums.
+#[derive(Clone, Copy)]
+pub struct BuiltInResourcesBuilder {
+    resources: BuiltInResources,
+}
+
+impl BuiltInResourcesBuilder {
+    pub fn new() -> Self {
+        BuiltInResourcesBuilder {
+            resources: BuiltInResources::default(),
+        }
+    }
+
+    /// The WebGL 1 minimums, which don't include `highp` in fragment
+    /// shaders.
+    pub fn for_webgl1() -> Self {
+        BuiltInResourcesBuilder::new()
+    }
+
+    /// The WebGL 2 minimums, which are those of GLES 3.0, where `highp` is
+    /// always available in fragment shaders.
+    pub fn for_webgl2() -> Self {
+        BuiltInResourcesBuilder::for_webgl1()
+            .fragment_precision_high(true)
+            .limit(Limit::MaxVertexAttribs, 16)
+            .limit(Limit::MaxVertexUniformVectors, 256)
+            .limit(Limit::MaxVaryingVectors, 15)
+            .limit(Limit::MaxVertexTextureImageUnits, 16)
+            .limit(Limit::MaxCombinedTextureImageUnits, 32)
+            .limit(Limit::MaxTextureImageUnits, 16)
+            .limit(Limit::MaxFragmentUniformVectors, 224)
+            .limit(Limit::MaxDrawBuffers, 4)
+            .limit(Limit::MaxVertexOutputVectors, 16)
+            .limit(Limit::MaxFragmentInputVectors, 15)
+            .limit(Limit::MinProgramTexelOffset, -8)
+            .limit(Limit::MaxProgramTexelOffset, 7)
+            .limit(Limit::MaxVertexUniformBlocks, 12)
+            .limit(Limit::MaxFragmentUniformBlocks, 12)
+            .limit(Limit::MaxUniformBufferBindings, 24)
+    }
+
+    #[inline]
+    pub fn extension(mut self, extension: Extension, enabled: bool) -> Self {
+        *extension.field(&mut self.resources) = enabled as c_int;
+        self
+    }
+
+    /// Enables every extension in `names` that the translator knows about,
+    /// as returned by `glGetString(GL_EXTENSIONS)` or
+    /// `getSupportedExtensions()`. Unknown names are ignored.
+    pub fn extensions<'a, I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        for extension in names.into_iter().filter_map(Extension::from_name) {
+            self = self.extension(extension, true);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn limit(mut self, limit: Limit, value: i32) -> Self {
+        *limit.field(&mut self.resources) = value;
+        self
+    }
+
+    /// Reads every limit that has a `gl_enum` through `get_integer`, which
+    /// should behave like `glGetIntegerv` and return `None` for parameters
+    /// the context doesn't support. Those limits keep their current value.
+    ///
+    /// The compute work group limits are indexed, see
+    /// `query_compute_work_group_limits`.
+    pub fn query_limits<F>(mut self, mut get_integer: F) -> Self
+    where
+        F: FnMut(u32) -> Option<i32>,
+    {
+        for &limit in Limit::ALL {
+            if let Some(value) = limit.gl_enum().and_then(&mut get_integer) {
+                self = self.limit(limit, value / limit.gl_divisor());
+            }
+        }
+        self
+    }
+
+    /// The `GL_MAX_COMPUTE_WORK_GROUP_COUNT` limits, in x, y, z order.
+    pub fn max_compute_work_group_count(mut self, count: [i32; 3]) -> Self {
+        unsafe { GLSLangSetMaxComputeWorkGroupCount(&mut self.resources, count.as_ptr()) };
+        self
+    }
+
+    /// The `GL_MAX_COMPUTE_WORK_GROUP_SIZE` limits, in x, y, z order.
+    pub fn max_compute_work_group_size(mut self, size: [i32; 3]) -> Self {
+        unsafe { GLSLangSetMaxComputeWorkGroupSize(&mut self.resources, size.as_ptr()) };
+        self
+    }
+
+    /// Reads the compute work group limits through `get_integer_indexed`,
+    /// which should behave like `glGetIntegeri_v`. A limit keeps its current
+    /// value unless all three of its dimensions are returned.
+    pub fn query_compute_work_group_limits<F>(mut self, mut get_integer_indexed: F) -> Self
+    where
+        F: FnMut(u32, u32) -> Option<i32>,
+    {
+        let mut get = |pname| -> Option<[i32; 3]> {
+            Some([
+                get_integer_indexed(pname, 0)?,
+                get_integer_indexed(pname, 1)?,
+                get_integer_indexed(pname, 2)?,
+            ])
+        };
+        // GL_MAX_COMPUTE_WORK_GROUP_COUNT
+        if let Some(count) = get(0x91BE) {
+            self = self.max_compute_work_group_count(count);
+        }
+        // GL_MAX_COMPUTE_WORK_GROUP_SIZE
+        if let Some(size) = get(0x91BF) {
+            self = self.max_compute_work_group_size(size);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn fragment_precision_high(mut self, enabled: bool) -> Self {
+        self.resources.FragmentPrecisionHigh = enabled as c_int;
+        self
+    }
+
+    /// The upper bound of `GL_ALIASED_POINT_SIZE_RANGE`, used by the
+    /// `clampPointSize` compile option.
+    #[inline]
+    pub fn max_point_size(mut self, size: f32) -> Self {
+        self.resources.MaxPointSize = size;
+        self
+    }
+
+    #[inline]
+    pub fn build(self) -> BuiltInResources {
+        self.resources
+    }
+}
+
+impl Default for BuiltInResourcesBuilder {
+    fn default() -> Self {
+        BuiltInResourcesBuilder::new()
+    }
+}
+
+impl BuiltInResources {
+    #[inline]
+    pub fn builder() -> BuiltInResourcesBuilder {
+        BuiltInResourcesBuilder::new()
+    }
+}
@@ -0,0 +1,100 @@
+/// A position in the source strings of a shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// Index of the source string.
+    pub string: u32,
+    /// One-based line number inside that source string.
+    pub line: u32,
+}
+
+/// Where each line of the object code came from, as told by the `#line`
+/// directives the translator writes with the `line_directives` option.
+///
+/// ANGLE only writes those directives for HLSL output, which is why
+/// `ShaderValidator::compile_with_source_map` rejects the other outputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    lines: Vec<Option<SourceLocation>>,
+    names: Vec<String>,
+}
+
+impl SourceMap {
+    /// Builds the map of `object_code`, whose directives number lines from
+    /// the start of the concatenated source strings. `line_starts` is the
+    /// first line of each source string, see `line_starts`, and `names` their
+    /// names, if they have any.
+    pub(super) fn new(object_code: &str, line_starts: &[u32], names: Vec<String>) -> Self {
+        let mut lines = Vec::new();
+        let mut current = None;
+        for text in object_code.lines() {
+            lines.push(current.map(|(string, line)| match string {
+                Some(string) => SourceLocation { string, line },
+                None => locate(line_starts, line),
+            }));
+            current = current.map(|(string, line)| (string, line + 1));
+
+            let mut words = text.split_whitespace();
+            if words.next() != Some("#line") {
+                continue;
+            }
+            if let Some(line) = words.next().and_then(|word| word.parse().ok()) {
+                let string = words.next().and_then(|word| word.parse().ok());
+                current = Some((string, line));
+            }
+        }
+        SourceMap { lines, names }
+    }
+
+    /// Returns where the one-based `line` of the object code came from.
+    pub fn lookup(&self, line: u32) -> Option<SourceLocation> {
+        let index = (line as usize).checked_sub(1)?;
+        self.lines.get(index).cloned().unwrap_or(None)
+    }
+
+    /// Returns the name of the source string `string`, for shaders compiled
+    /// with `ShaderValidator::compile_named_with_source_map`.
+    pub fn source_name(&self, string: u32) -> Option<&str> {
+        self.names.get(string as usize).map(|name| &**name)
+    }
+
+    /// Iterates over the one-based lines of the object code that have a
+    /// known origin, with that origin.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, SourceLocation)> + '_ {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, location)| location.map(|location| (i as u32 + 1, location)))
+    }
+
+    /// Whether no line of the object code has a known origin.
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(Option::is_none)
+    }
+}
+
+/// Returns the line of the concatenation of `strings` each of them starts
+/// on, when a newline is added after those that don't end with one.
+pub(super) fn line_starts(strings: &[&str]) -> Vec<u32> {
+    let mut starts = Vec::with_capacity(strings.len());
+    let mut line = 1;
+    for string in strings {
+        starts.push(line);
+        line += string.matches('\n').count() as u32;
+        if !string.ends_with('\n') {
+            line += 1;
+        }
+    }
+    starts
+}
+
+/// Converts a line of the concatenated source strings to a location in one
+/// of them. Without `line_starts`, there was a single string.
+pub(super) fn locate(line_starts: &[u32], line: u32) -> SourceLocation {
+    match line_starts.iter().rposition(|&start| start <= line) {
+        Some(string) => SourceLocation {
+            string: string as u32,
+            line: line - line_starts[string] + 1,
+        },
+        None => SourceLocation { string: 0, line },
+    }
+}
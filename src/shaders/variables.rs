@@ -0,0 +1,377 @@
+use super::ffi::{GLSLangInterfaceBlock, GLSLangShaderVariable};
+
+use std::cmp;
+use std::os::raw::{c_char, c_int, c_void};
+use std::slice;
+use std::vec;
+
+/// The interpolation qualifier of a varying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    Smooth,
+    Centroid,
+    Sample,
+    Flat,
+    NoPerspective,
+}
+
+impl Interpolation {
+    fn from_angle(interpolation: c_int) -> Interpolation {
+        // Matches `sh::InterpolationType`.
+        match interpolation {
+            1 => Interpolation::Centroid,
+            2 => Interpolation::Sample,
+            3 => Interpolation::Flat,
+            4 => Interpolation::NoPerspective,
+            _ => Interpolation::Smooth,
+        }
+    }
+}
+
+/// A variable declared by a shader, as reflected by ANGLE after compiling
+/// with the `variables` option.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderVariable {
+    /// The GL type enum, for example `GL_FLOAT_VEC4`. Structs have a type
+    /// of `GL_NONE` and list their members in `fields`.
+    pub ty: u32,
+    /// The GL precision enum, for example `GL_HIGH_FLOAT`, or `GL_NONE`.
+    pub precision: u32,
+    /// The name in the original shader.
+    pub name: String,
+    /// The name in the translated shader.
+    pub mapped_name: String,
+    /// The name of the struct type, for struct variables, or of the block
+    /// type, for shader I/O blocks.
+    pub struct_name: String,
+    /// The name of the block type in the translated shader, for shader I/O
+    /// blocks. ANGLE leaves it empty for structs.
+    pub mapped_struct_name: String,
+    /// Sizes of each array dimension, innermost first. Empty for variables
+    /// that are not arrays.
+    pub array_sizes: Vec<u32>,
+    pub static_use: bool,
+    pub active: bool,
+    pub fields: Vec<ShaderVariable>,
+    /// Only meaningful for interface block fields.
+    pub is_row_major_layout: bool,
+    /// The `location` layout qualifier, or -1.
+    pub location: i32,
+    /// The `binding` layout qualifier, or -1.
+    pub binding: i32,
+    /// The GL enum of the `format` layout qualifier of images, or `GL_NONE`.
+    pub image_unit_format: u32,
+    /// The `offset` layout qualifier of atomic counters, or -1.
+    pub offset: i32,
+    pub raster_ordered: bool,
+    pub readonly: bool,
+    pub writeonly: bool,
+    /// Whether this fragment output is also read through framebuffer fetch.
+    pub is_fragment_in_out: bool,
+    /// The `index` layout qualifier of `EXT_blend_func_extended` outputs,
+    /// or -1.
+    pub index: i32,
+    /// Whether this fragment output has the `EXT_YUV_target` `yuv` qualifier.
+    pub yuv: bool,
+    /// Only meaningful for varyings.
+    pub interpolation: Interpolation,
+    pub is_invariant: bool,
+    /// Whether this varying is an I/O block, whose members are in `fields`.
+    pub is_shader_io_block: bool,
+    /// Whether this is a tessellation `patch` varying.
+    pub is_patch: bool,
+}
+
+impl ShaderVariable {
+    pub fn is_array(&self) -> bool {
+        !self.array_sizes.is_empty()
+    }
+
+    pub fn is_struct(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    /// The total number of array elements, or 1 for variables that are not
+    /// arrays. Saturates at `u32::MAX`.
+    pub fn array_size_product(&self) -> u32 {
+        self.array_sizes
+            .iter()
+            .fold(1u32, |product, &size| product.saturating_mul(size))
+    }
+
+    /// Whether this is one of the `gl_` built-in variables.
+    pub fn is_built_in(&self) -> bool {
+        self.name.starts_with("gl_")
+    }
+}
+
+/// The memory layout of an interface block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockLayout {
+    Std140,
+    Std430,
+    Packed,
+    Shared,
+}
+
+impl BlockLayout {
+    fn from_angle(layout: c_int) -> BlockLayout {
+        // Matches `sh::BlockLayoutType`.
+        match layout {
+            1 => BlockLayout::Std430,
+            2 => BlockLayout::Packed,
+            3 => BlockLayout::Shared,
+            _ => BlockLayout::Std140,
+        }
+    }
+}
+
+/// Whether an interface block is backed by a uniform buffer or a shader
+/// storage buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockType {
+    Uniform,
+    Buffer,
+}
+
+/// A uniform block or shader storage block declared by a shader.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InterfaceBlock {
+    /// The block name in the original shader, used by `getUniformBlockIndex`.
+    pub name: String,
+    /// The block name in the translated shader.
+    pub mapped_name: String,
+    /// The instance name, or an empty string for blocks without one. Fields
+    /// of blocks with an instance name are accessed through it.
+    pub instance_name: String,
+    /// The number of array elements, or 0 for blocks that are not arrays.
+    pub array_size: u32,
+    pub layout: BlockLayout,
+    /// Deprecated in ANGLE, matrix packing should be read from the fields.
+    pub is_row_major_layout: bool,
+    /// The `binding` layout qualifier, or -1.
+    pub binding: i32,
+    pub static_use: bool,
+    pub active: bool,
+    pub block_type: BlockType,
+    pub fields: Vec<ShaderVariable>,
+}
+
+impl InterfaceBlock {
+    pub fn is_array(&self) -> bool {
+        self.array_size > 0
+    }
+
+    /// The number of buffer bindings the block uses.
+    pub fn element_count(&self) -> u32 {
+        cmp::max(1, self.array_size)
+    }
+
+    /// Whether this is one of the `gl_` built-in blocks.
+    pub fn is_built_in(&self) -> bool {
+        self.name.starts_with("gl_")
+    }
+}
+
+/// Rebuilds the variable trees reported by `VisitShaderVariables` in the
+/// glue code, which lists each variable before its fields.
+pub(super) struct VariableCollector {
+    flat: Vec<(ShaderVariable, usize)>,
+}
+
+impl VariableCollector {
+    pub fn new() -> Self {
+        VariableCollector { flat: Vec::new() }
+    }
+
+    pub fn closure(&mut self) -> *mut c_void {
+        self as *mut VariableCollector as *mut c_void
+    }
+
+    pub unsafe extern "C" fn each(closure: *mut c_void, variable: *const GLSLangShaderVariable) {
+        // Safety: code in or called from this function must not unwind into
+        // C++.
+        let collector = &mut *(closure as *mut VariableCollector);
+        collector.push(&*variable);
+    }
+
+    unsafe fn push(&mut self, variable: &GLSLangShaderVariable) {
+        let array_sizes = if variable.arraySizesLength == 0 {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(variable.arraySizes, variable.arraySizesLength).to_vec()
+        };
+        self.flat.push((
+            ShaderVariable {
+                ty: variable.type_,
+                precision: variable.precision,
+                name: super::to_string(variable.name, variable.nameLength),
+                mapped_name: super::to_string(variable.mappedName, variable.mappedNameLength),
+                struct_name: super::to_string(
+                    variable.structOrBlockName,
+                    variable.structOrBlockNameLength,
+                ),
+                mapped_struct_name: super::to_string(
+                    variable.mappedStructOrBlockName,
+                    variable.mappedStructOrBlockNameLength,
+                ),
+                array_sizes,
+                static_use: variable.staticUse != 0,
+                active: variable.active != 0,
+                fields: Vec::new(),
+                is_row_major_layout: variable.isRowMajorLayout != 0,
+                location: variable.location,
+                binding: variable.binding,
+                image_unit_format: variable.imageUnitFormat,
+                offset: variable.offset,
+                raster_ordered: variable.rasterOrdered != 0,
+                readonly: variable.readonly != 0,
+                writeonly: variable.writeonly != 0,
+                is_fragment_in_out: variable.isFragmentInOut != 0,
+                index: variable.index,
+                yuv: variable.yuv != 0,
+                interpolation: Interpolation::from_angle(variable.interpolation),
+                is_invariant: variable.isInvariant != 0,
+                is_shader_io_block: variable.isShaderIOBlock != 0,
+                is_patch: variable.isPatch != 0,
+            },
+            variable.fieldsLength,
+        ));
+    }
+
+    pub fn finish(self) -> Vec<ShaderVariable> {
+        fn take(flat: &mut vec::IntoIter<(ShaderVariable, usize)>) -> Option<ShaderVariable> {
+            let (mut variable, fields) = flat.next()?;
+            variable.fields = (0..fields).filter_map(|_| take(flat)).collect();
+            Some(variable)
+        }
+
+        let mut flat = self.flat.into_iter();
+        let mut variables = Vec::new();
+        while let Some(variable) = take(&mut flat) {
+            variables.push(variable);
+        }
+        variables
+    }
+}
+
+/// Flattens variable trees the same way the glue code reports them, so that
+/// they can be handed back to ANGLE. The result borrows from `variables`.
+pub(super) fn flatten(variables: &[ShaderVariable], flat: &mut Vec<GLSLangShaderVariable>) {
+    for variable in variables {
+        flat.push(GLSLangShaderVariable {
+            type_: variable.ty,
+            precision: variable.precision,
+            name: variable.name.as_ptr() as *const c_char,
+            nameLength: variable.name.len(),
+            mappedName: variable.mapped_name.as_ptr() as *const c_char,
+            mappedNameLength: variable.mapped_name.len(),
+            structOrBlockName: variable.struct_name.as_ptr() as *const c_char,
+            structOrBlockNameLength: variable.struct_name.len(),
+            mappedStructOrBlockName: variable.mapped_struct_name.as_ptr() as *const c_char,
+            mappedStructOrBlockNameLength: variable.mapped_struct_name.len(),
+            arraySizes: variable.array_sizes.as_ptr(),
+            arraySizesLength: variable.array_sizes.len(),
+            staticUse: variable.static_use as c_int,
+            active: variable.active as c_int,
+            isRowMajorLayout: variable.is_row_major_layout as c_int,
+            location: variable.location,
+            binding: variable.binding,
+            imageUnitFormat: variable.image_unit_format,
+            offset: variable.offset,
+            rasterOrdered: variable.raster_ordered as c_int,
+            readonly: variable.readonly as c_int,
+            writeonly: variable.writeonly as c_int,
+            isFragmentInOut: variable.is_fragment_in_out as c_int,
+            index: variable.index,
+            yuv: variable.yuv as c_int,
+            interpolation: variable.interpolation as c_int,
+            isInvariant: variable.is_invariant as c_int,
+            isShaderIOBlock: variable.is_shader_io_block as c_int,
+            isPatch: variable.is_patch as c_int,
+            fieldsLength: variable.fields.len(),
+        });
+        flatten(&variable.fields, flat);
+    }
+}
+
+/// The counterpart of `flatten` for blocks, whose fields are flattened
+/// separately. The result borrows from `block`.
+pub(super) fn flatten_block(block: &InterfaceBlock) -> GLSLangInterfaceBlock {
+    GLSLangInterfaceBlock {
+        name: block.name.as_ptr() as *const c_char,
+        nameLength: block.name.len(),
+        mappedName: block.mapped_name.as_ptr() as *const c_char,
+        mappedNameLength: block.mapped_name.len(),
+        instanceName: block.instance_name.as_ptr() as *const c_char,
+        instanceNameLength: block.instance_name.len(),
+        arraySize: block.array_size,
+        layout: block.layout as c_int,
+        isRowMajorLayout: block.is_row_major_layout as c_int,
+        binding: block.binding,
+        staticUse: block.static_use as c_int,
+        active: block.active as c_int,
+        blockType: block.block_type as c_int,
+    }
+}
+
+/// Collects the blocks reported by `VisitInterfaceBlocks` in the glue code,
+/// each of which is followed by its fields.
+pub(super) struct InterfaceBlockCollector {
+    blocks: Vec<(InterfaceBlock, VariableCollector)>,
+}
+
+impl InterfaceBlockCollector {
+    pub fn new() -> Self {
+        InterfaceBlockCollector { blocks: Vec::new() }
+    }
+
+    pub fn closure(&mut self) -> *mut c_void {
+        self as *mut InterfaceBlockCollector as *mut c_void
+    }
+
+    pub unsafe extern "C" fn each_block(closure: *mut c_void, block: *const GLSLangInterfaceBlock) {
+        let collector = &mut *(closure as *mut InterfaceBlockCollector);
+        let block = &*block;
+        collector.blocks.push((
+            InterfaceBlock {
+                name: super::to_string(block.name, block.nameLength),
+                mapped_name: super::to_string(block.mappedName, block.mappedNameLength),
+                instance_name: super::to_string(block.instanceName, block.instanceNameLength),
+                array_size: block.arraySize,
+                layout: BlockLayout::from_angle(block.layout),
+                is_row_major_layout: block.isRowMajorLayout != 0,
+                binding: block.binding,
+                static_use: block.staticUse != 0,
+                active: block.active != 0,
+                block_type: if block.blockType == 0 {
+                    BlockType::Uniform
+                } else {
+                    BlockType::Buffer
+                },
+                fields: Vec::new(),
+            },
+            VariableCollector::new(),
+        ));
+    }
+
+    pub unsafe extern "C" fn each_field(
+        closure: *mut c_void,
+        variable: *const GLSLangShaderVariable,
+    ) {
+        let collector = &mut *(closure as *mut InterfaceBlockCollector);
+        if let Some(&mut (_, ref mut fields)) = collector.blocks.last_mut() {
+            fields.push(&*variable);
+        }
+    }
+
+    pub fn finish(self) -> Vec<InterfaceBlock> {
+        self.blocks
+            .into_iter()
+            .map(|(mut block, fields)| {
+                block.fields = fields.finish();
+                block
+            })
+            .collect()
+    }
+}
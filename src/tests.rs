@@ -32,7 +32,6 @@ fn test_egl_linkage() {
 #[test]
 fn test_translation_complex() {
     init();
-    const FRAGMENT_SHADER: u32 = 0x8B30;
     let source = "
 precision mediump float;
 varying vec2 vTextureCoord;
@@ -42,7 +41,8 @@ void main() {
 }
 ";
     let resources = BuiltInResources::default();
-    let compiler = ShaderValidator::for_webgl(FRAGMENT_SHADER, Output::Glsl, &resources).unwrap();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Glsl, &resources).unwrap();
 
     assert!(compiler.compile_and_translate(&[source]).is_ok());
 
@@ -60,12 +60,12 @@ gl_FragColor = vec4(0, 1, 0, 1);  // green
   (gl_FragColor = vec4(0.0, 1.0, 0.0, 1.0));
 }
 "#;
-    const FRAGMENT_SHADER: u32 = 0x8B30;
 
     init();
 
     let resources = BuiltInResources::default();
-    let compiler = ShaderValidator::for_webgl(FRAGMENT_SHADER, Output::Glsl, &resources).unwrap();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Glsl, &resources).unwrap();
 
     let result = compiler.compile_and_translate(&[SHADER]).unwrap();
     // Use result.contains instead of equal because Angle may add some extensions such as
@@ -83,14 +83,1691 @@ gl_FragColor = vec4(0, 1, 0, 1);  // green
   (gl_FragColor = vec4(0.0, 1.0, 0.0, 1.0));
 }
 "#;
-    const FRAGMENT_SHADER: u32 = 0x8B30;
 
     init();
 
-    let compiler =
-        ShaderValidator::for_webgl(FRAGMENT_SHADER, Output::Essl, &BuiltInResources::default())
-            .expect("Failed to create a validator for essl");
+    let compiler = ShaderValidator::for_webgl(
+        ShaderType::Fragment,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .expect("Failed to create a validator for essl");
 
     let result = compiler.compile_and_translate(&[SHADER]).unwrap();
     assert!(result.contains(EXPECTED));
 }
+
+#[test]
+fn test_compile_error() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl(
+        ShaderType::Fragment,
+        Output::Glsl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+
+    let err = compiler
+        .compile_and_translate(&["void main() {\n  gl_FragColor = vec4(foo);\n}"])
+        .unwrap_err();
+    assert!(err.info_log().unwrap().contains("undeclared identifier"));
+    let diagnostic = &err.diagnostics()[0];
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.source_string, Some(0));
+    assert_eq!(diagnostic.line, Some(2));
+    assert_eq!(diagnostic.token.as_deref(), Some("foo"));
+
+    match compiler.compile_and_translate(&["void main() {}\0"]) {
+        Err(CompileError::InvalidInput { string, position }) => {
+            assert_eq!((string, position), (0, 14))
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_info_log() {
+    let log = "\
+ERROR: 0:3: 'foo' : undeclared identifier
+WARNING: 1:7: 'GL_OES_foo' : extension is not supported
+ERROR: 2:? : 'bar' : unknown line
+ERROR: 0:5: '' : user-supplied #error
+ERROR: 0:9: ''' : invalid character
+ERROR: 0:4: '=' : cannot convert from 'const int' to 'highp float'
+UNKOWN ERROR: 0:1: 'x' : unknown severity
+ERROR: Missing main()
+ERROR: Call stack too deep (larger than 16) with the following call chain: main -> f
+0:1: Code block
+ERROR: 2 compilation errors.  No code generated.
+";
+    let diagnostics = parse_info_log(log);
+    let expected = [
+        (
+            Severity::Error,
+            Some(0),
+            Some(3),
+            Some("foo"),
+            "undeclared identifier",
+        ),
+        (
+            Severity::Warning,
+            Some(1),
+            Some(7),
+            Some("GL_OES_foo"),
+            "extension is not supported",
+        ),
+        (Severity::Error, Some(2), None, Some("bar"), "unknown line"),
+        (
+            Severity::Error,
+            Some(0),
+            Some(5),
+            None,
+            "user-supplied #error",
+        ),
+        (
+            Severity::Error,
+            Some(0),
+            Some(9),
+            Some("'"),
+            "invalid character",
+        ),
+        (
+            Severity::Error,
+            Some(0),
+            Some(4),
+            Some("="),
+            "cannot convert from 'const int' to 'highp float'",
+        ),
+        (
+            Severity::Error,
+            Some(0),
+            Some(1),
+            Some("x"),
+            "unknown severity",
+        ),
+        (Severity::Error, None, None, None, "Missing main()"),
+        (
+            Severity::Error,
+            None,
+            None,
+            None,
+            "Call stack too deep (larger than 16) with the following call chain: main -> f",
+        ),
+        (
+            Severity::Error,
+            None,
+            None,
+            None,
+            "2 compilation errors.  No code generated.",
+        ),
+    ];
+    assert_eq!(diagnostics.len(), expected.len());
+    for (diagnostic, &(severity, source_string, line, token, message)) in
+        diagnostics.iter().zip(expected.iter())
+    {
+        assert_eq!(diagnostic.severity, severity);
+        assert_eq!(diagnostic.source_string, source_string);
+        assert_eq!(diagnostic.line, line);
+        assert_eq!(diagnostic.token.as_deref(), token);
+        assert_eq!(diagnostic.message, message);
+    }
+
+    // Formatting round-trips everything but the misspelled prefix.
+    let formatted = diagnostics
+        .iter()
+        .map(|d| format!("{}\n", d))
+        .collect::<String>();
+    assert_eq!(
+        formatted,
+        log.replace("UNKOWN ERROR", "ERROR")
+            .replace("0:1: Code block\n", "")
+    );
+}
+
+#[test]
+fn test_diagnostics_warning() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl(
+        ShaderType::Fragment,
+        Output::Glsl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+
+    compiler
+        .compile_and_translate(&[
+            "#extension GL_OES_unknown : warn\nvoid main() {\n  gl_FragColor = vec4(1.0);\n}",
+        ])
+        .unwrap();
+    let diagnostics = compiler.diagnostics();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].line, Some(1));
+    assert_eq!(diagnostics[0].token.as_deref(), Some("GL_OES_unknown"));
+}
+
+#[test]
+fn test_unsupported_shader_type() {
+    init();
+
+    let resources = BuiltInResources::default();
+    match ShaderValidator::for_webgl2(ShaderType::Compute, Output::Essl, &resources) {
+        Err(CompileError::UnsupportedShaderType { shader_type, spec }) => {
+            assert_eq!(shader_type, ShaderType::Compute);
+            assert_eq!(spec, ShaderSpec::WebGL2);
+        }
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+    assert!(ShaderValidator::new(
+        ShaderType::Compute,
+        ShaderSpec::WebGL3,
+        Output::Essl,
+        &resources
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_shader_spec_helpers() {
+    assert!(ShaderSpec::WebGL3.is_webgl());
+    assert!(!ShaderSpec::Gles31.is_webgl());
+    assert!(ShaderSpec::GlCore.is_desktop());
+    assert!(!ShaderSpec::Gles32.is_desktop());
+    assert!(ShaderSpec::Gles32.supports(ShaderType::Geometry));
+    assert!(!ShaderSpec::WebGL3.supports(ShaderType::TessControl));
+}
+
+#[test]
+fn test_gles31_compute() {
+    init();
+
+    let compiler = ShaderValidator::new(
+        ShaderType::Compute,
+        ShaderSpec::Gles31,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    let source = "#version 310 es
+layout(local_size_x = 4) in;
+layout(std430, binding = 0) buffer Data { uint values[]; };
+void main() {
+    values[gl_GlobalInvocationID.x] = gl_GlobalInvocationID.x;
+}
+";
+    compiler
+        .compile_and_translate(&[source])
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+#[test]
+fn test_desktop_gl() {
+    init();
+
+    let compiler = ShaderValidator::new(
+        ShaderType::Fragment,
+        ShaderSpec::GlCore,
+        Output::Glsl330Core,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    let source = "#version 330
+in vec2 uv;
+out vec4 color;
+void main() {
+    color = vec4(uv, 0.0, 1.0);
+}
+";
+    compiler
+        .compile_and_translate(&[source])
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+#[test]
+fn test_hlsl_registers() {
+    init();
+
+    let source = "#version 300 es
+precision mediump float;
+uniform Lights { vec4 color; } lights;
+uniform sampler2D tex;
+uniform vec4 tint;
+in vec2 uv;
+out vec4 fragColor;
+void main() {
+    fragColor = texture(tex, uv) * lights.color * tint;
+}
+";
+    let resources = BuiltInResources::default();
+    let compiler =
+        ShaderValidator::for_webgl2(ShaderType::Fragment, Output::Hlsl41, &resources).unwrap();
+    let hlsl = compiler
+        .compile_and_translate(&[source])
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(hlsl.contains("cbuffer"), "{}", hlsl);
+
+    let registers = compiler.uniform_register_map();
+    assert!(registers.contains_key("tex"), "{:?}", registers);
+    assert!(registers.contains_key("tint"), "{:?}", registers);
+    assert!(compiler.uniform_block_register("Lights").is_some());
+    assert_eq!(compiler.uniform_block_register("Missing"), None);
+    assert_eq!(compiler.shader_storage_block_register("Lights"), None);
+    assert!(!compiler.uniform_block_uses_structured_buffer("Lights"));
+    assert!(compiler.image2d_register_index().is_some());
+
+    let compiler =
+        ShaderValidator::for_webgl2(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler.compile_and_translate(&[source]).unwrap();
+    assert!(compiler.uniform_register_map().is_empty());
+    assert_eq!(compiler.uniform_block_register("Lights"), None);
+    assert_eq!(compiler.image2d_register_index(), None);
+}
+
+#[test]
+fn test_compile_options() {
+    use std::collections::HashSet;
+
+    let options = CompileOptions::new().object_code(true).variables(true);
+    assert_eq!(options.enabled_flags(), ["object_code", "variables"]);
+    assert_eq!(format!("{:?}", options), r#"{"object_code", "variables"}"#);
+    assert_eq!(options.objectCode(), 1);
+    assert_eq!(
+        options,
+        CompileOptions::new().variables(true).object_code(true)
+    );
+    assert_ne!(options, options.variables(false));
+
+    let presets: HashSet<_> = vec![
+        CompileOptions::mozangle(),
+        CompileOptions::strict_webgl(),
+        CompileOptions::desktop_driver_workarounds(),
+        CompileOptions::debug(),
+        CompileOptions::mozangle(),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(presets.len(), 4);
+
+    init();
+
+    let compiler = ShaderValidator::for_webgl(
+        ShaderType::Fragment,
+        Output::Glsl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    for options in presets {
+        compiler
+            .compile(&["void main() { gl_FragColor = vec4(1.0); }"], options)
+            .unwrap_or_else(|e| panic!("{:?}: {}", options, e));
+    }
+}
+
+#[test]
+fn test_builtin_resources_builder() {
+    assert_eq!(
+        Extension::from_name("GL_OES_standard_derivatives"),
+        Some(Extension::OesStandardDerivatives)
+    );
+    assert_eq!(
+        Extension::from_name("WEBGL_draw_buffers"),
+        Some(Extension::ExtDrawBuffers)
+    );
+    assert_eq!(Extension::from_name("GL_FOO_bar"), None);
+
+    let resources = BuiltInResourcesBuilder::for_webgl2()
+        .extensions(vec!["GL_EXT_frag_depth", "GL_FOO_bar"])
+        .query_limits(|pname| match pname {
+            0x8869 => Some(8),   // GL_MAX_VERTEX_ATTRIBS
+            0x9125 => Some(120), // GL_MAX_FRAGMENT_INPUT_COMPONENTS
+            _ => None,
+        })
+        .build();
+    assert_eq!(resources.EXT_frag_depth, 1);
+    assert_eq!(resources.MaxVertexAttribs, 8);
+    assert_eq!(resources.MaxFragmentInputVectors, 30);
+    assert_eq!(resources.MaxDrawBuffers, 4);
+    assert_eq!(resources.FragmentPrecisionHigh, 1);
+
+    init();
+
+    let source = "#extension GL_EXT_frag_depth : require
+void main() { gl_FragDepthEXT = 0.5; }";
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Glsl, &resources).unwrap();
+    assert!(compiler.compile(&[source], CompileOptions::new()).is_ok());
+
+    let resources = BuiltInResources::builder().build();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Glsl, &resources).unwrap();
+    assert!(compiler.compile(&[source], CompileOptions::new()).is_err());
+
+    assert_eq!(
+        BuiltInResourcesBuilder::for_webgl1()
+            .build()
+            .FragmentPrecisionHigh,
+        0
+    );
+
+    let local_size = |x| {
+        format!(
+            "#version 310 es\nlayout(local_size_x = {}) in;\nvoid main() {{}}\n",
+            x
+        )
+    };
+    let resources = BuiltInResourcesBuilder::new()
+        .max_compute_work_group_size([8, 8, 8])
+        .query_compute_work_group_limits(|pname, index| match pname {
+            0x91BF if index == 0 => Some(16), // GL_MAX_COMPUTE_WORK_GROUP_SIZE
+            0x91BF => Some(4),
+            _ => None,
+        })
+        .build();
+    let compiler = ShaderValidator::new(
+        ShaderType::Compute,
+        ShaderSpec::Gles31,
+        Output::Essl,
+        &resources,
+    )
+    .unwrap();
+    assert!(compiler
+        .compile(&[&local_size(16)], CompileOptions::new())
+        .is_ok());
+    assert!(compiler
+        .compile(&[&local_size(32)], CompileOptions::new())
+        .is_err());
+}
+
+#[test]
+fn test_uniforms() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl2(
+        ShaderType::Fragment,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+precision mediump float;
+struct Light { vec3 position; float intensity[2]; };
+uniform Light lights[3];
+uniform sampler2D tex;
+uniform highp mat4 unused;
+out vec4 color;
+void main() {
+    color = texture(tex, lights[0].position.xy) * lights[1].intensity[1];
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let uniforms = compiler.uniforms();
+    let lights = uniforms.iter().find(|u| u.name == "lights").unwrap();
+    assert_eq!(lights.ty, 0);
+    assert_eq!(lights.struct_name, "Light");
+    assert_eq!(lights.array_sizes, vec![3]);
+    assert!(lights.static_use);
+    assert!(lights.is_struct());
+    let fields: Vec<_> = lights.fields.iter().map(|f| &*f.name).collect();
+    assert_eq!(fields, ["position", "intensity"]);
+    assert_eq!(lights.fields[0].ty, 0x8B51); // GL_FLOAT_VEC3
+    assert_eq!(lights.fields[0].precision, 0x8DF1); // GL_MEDIUM_FLOAT
+    assert_eq!(lights.fields[1].array_sizes, vec![2]);
+
+    let tex = uniforms.iter().find(|u| u.name == "tex").unwrap();
+    assert_eq!(tex.ty, 0x8B5E); // GL_SAMPLER_2D
+    assert!(!tex.is_array());
+    assert_eq!(tex.array_size_product(), 1);
+
+    let unused = uniforms.iter().find(|u| u.name == "unused").unwrap();
+    assert_eq!(unused.ty, 0x8B5C); // GL_FLOAT_MAT4
+    assert!(!unused.static_use);
+}
+
+#[test]
+fn test_attributes() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl2(
+        ShaderType::Vertex,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+layout(location = 3) in vec4 position;
+in vec2 uv;
+in float unused;
+out vec2 v_uv;
+void main() {
+    v_uv = uv;
+    gl_Position = position;
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let attributes = compiler.attributes();
+    assert_eq!(attributes.len(), 3);
+
+    let position = attributes.iter().find(|a| a.name == "position").unwrap();
+    assert_eq!(position.location, 3);
+    assert_eq!(position.ty, 0x8B52); // GL_FLOAT_VEC4
+    assert!(position.static_use);
+    assert!(!position.mapped_name.is_empty());
+
+    let uv = attributes.iter().find(|a| a.name == "uv").unwrap();
+    assert_eq!(uv.location, -1);
+    assert_eq!(uv.ty, 0x8B50); // GL_FLOAT_VEC2
+
+    let unused = attributes.iter().find(|a| a.name == "unused").unwrap();
+    assert!(!unused.static_use);
+}
+
+#[test]
+fn test_varyings() {
+    init();
+
+    let resources = BuiltInResources::default();
+    let vertex = ShaderValidator::for_webgl2(ShaderType::Vertex, Output::Essl, &resources).unwrap();
+    vertex
+        .compile(
+            &["#version 300 es
+in vec4 position;
+flat out int id;
+centroid out vec2 uv;
+invariant gl_Position;
+void main() {
+    id = 1;
+    uv = position.xy;
+    gl_Position = position;
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    assert!(vertex.input_varyings().is_empty());
+    let outputs = vertex.output_varyings();
+    assert_eq!(vertex.varyings(), outputs);
+
+    let id = outputs.iter().find(|v| v.name == "id").unwrap();
+    assert_eq!(id.interpolation, Interpolation::Flat);
+    assert_eq!(id.ty, 0x1404); // GL_INT
+    let uv = outputs.iter().find(|v| v.name == "uv").unwrap();
+    assert_eq!(uv.interpolation, Interpolation::Centroid);
+    let position = outputs.iter().find(|v| v.name == "gl_Position").unwrap();
+    assert!(position.is_built_in());
+    assert!(position.is_invariant);
+
+    let fragment =
+        ShaderValidator::for_webgl2(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    fragment
+        .compile(
+            &["#version 300 es
+precision mediump float;
+flat in int id;
+centroid in vec2 uv;
+out vec4 color;
+void main() {
+    color = vec4(uv, float(id), 1.0);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let inputs = fragment.input_varyings();
+    assert_eq!(inputs.len(), 2);
+    assert!(inputs.iter().all(|v| v.static_use && !v.is_patch));
+    assert!(fragment.output_varyings().is_empty());
+}
+
+#[test]
+fn test_interface_blocks() {
+    init();
+
+    let compiler = ShaderValidator::new(
+        ShaderType::Compute,
+        ShaderSpec::Gles31,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 310 es
+layout(local_size_x = 1) in;
+layout(std140, binding = 2) uniform Params { mat4 transform; vec4 scale; } params;
+layout(std430, binding = 0) buffer Data { float values[]; };
+void main() {
+    values[0] = params.transform[0][0] * params.scale.x;
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let uniform_blocks = compiler.uniform_blocks();
+    assert_eq!(uniform_blocks.len(), 1);
+    let params = &uniform_blocks[0];
+    assert_eq!(params.name, "Params");
+    assert_eq!(params.instance_name, "params");
+    assert_eq!(params.layout, BlockLayout::Std140);
+    assert_eq!(params.block_type, BlockType::Uniform);
+    assert_eq!(params.binding, 2);
+    assert!(!params.is_array());
+    assert_eq!(params.element_count(), 1);
+    assert!(params.static_use);
+    let fields: Vec<_> = params.fields.iter().map(|f| &*f.name).collect();
+    assert_eq!(fields, ["transform", "scale"]);
+
+    let storage_blocks = compiler.shader_storage_blocks();
+    assert_eq!(storage_blocks.len(), 1);
+    let data = &storage_blocks[0];
+    assert_eq!(data.name, "Data");
+    assert_eq!(data.instance_name, "");
+    assert_eq!(data.layout, BlockLayout::Std430);
+    assert_eq!(data.block_type, BlockType::Buffer);
+    assert_eq!(data.fields[0].name, "values");
+
+    assert_eq!(compiler.interface_blocks().len(), 2);
+}
+
+#[test]
+fn test_block_layout() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl2(
+        ShaderType::Vertex,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+struct Light { vec3 position; float intensity; };
+layout(std140) uniform Scene {
+    float time;
+    vec3 direction;
+    float weights[2];
+    layout(row_major) mat3 rotation;
+    Light lights[2];
+} scene;
+void main() {
+    gl_Position = vec4(scene.direction * scene.time, scene.weights[1]);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let scene = &compiler.uniform_blocks()[0];
+
+    let std140 = scene.compute_layout();
+    let member = |name: &str| std140.members[name];
+    assert_eq!(member("Scene.time").offset, 0);
+    assert_eq!(member("Scene.direction").offset, 16);
+    assert_eq!(member("Scene.weights").offset, 32);
+    assert_eq!(member("Scene.weights").array_stride, 16);
+    assert_eq!(member("Scene.rotation").offset, 64);
+    assert_eq!(member("Scene.rotation").matrix_stride, 16);
+    assert!(member("Scene.rotation").is_row_major_matrix);
+    assert_eq!(member("Scene.lights[0].position").offset, 112);
+    assert_eq!(member("Scene.lights[0].intensity").offset, 124);
+    assert_eq!(member("Scene.lights[1].position").offset, 128);
+    assert_eq!(
+        member("Scene.lights[0].position").top_level_array_stride,
+        16
+    );
+    assert_eq!(std140.size, 144);
+
+    let std430 = scene.compute_layout_as(BlockLayout::Std430);
+    assert_eq!(std430.members["Scene.weights"].array_stride, 4);
+    assert!(std430.size < std140.size);
+}
+
+#[test]
+fn test_output_variables() {
+    init();
+
+    let resources = BuiltInResources::builder()
+        .extension(Extension::ExtBlendFuncExtended, true)
+        .extension(Extension::ExtShaderFramebufferFetch, true)
+        .limit(Limit::MaxDualSourceDrawBuffers, 1)
+        .limit(Limit::MaxDrawBuffers, 4)
+        .build();
+    let compiler =
+        ShaderValidator::for_webgl2(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+#extension GL_EXT_blend_func_extended : require
+precision mediump float;
+layout(location = 0, index = 0) out vec4 color;
+layout(location = 0, index = 1) out vec4 secondary;
+void main() {
+    color = vec4(1.0);
+    secondary = vec4(0.5);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let outputs = compiler.output_variables();
+    assert_eq!(outputs.len(), 2);
+    let secondary = outputs.iter().find(|o| o.name == "secondary").unwrap();
+    assert_eq!(secondary.location, 0);
+    assert_eq!(secondary.index, 1);
+    assert_eq!(secondary.ty, 0x8B52); // GL_FLOAT_VEC4
+    assert!(!secondary.is_fragment_in_out);
+
+    compiler
+        .compile(
+            &["#version 300 es
+#extension GL_EXT_shader_framebuffer_fetch : require
+precision mediump float;
+inout vec4 color[2];
+void main() {
+    color[1] += vec4(0.5);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+
+    let outputs = compiler.output_variables();
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].is_fragment_in_out);
+    assert_eq!(outputs[0].array_sizes, vec![2]);
+
+    let compiler = ShaderValidator::for_webgl(
+        ShaderType::Fragment,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["void main() { gl_FragColor = vec4(1.0); }"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+    let outputs = compiler.output_variables();
+    assert_eq!(outputs[0].name, "gl_FragColor");
+    assert!(outputs[0].is_built_in());
+}
+
+#[test]
+fn test_compute_metadata() {
+    init();
+
+    let resources = BuiltInResources::default();
+    let compiler = ShaderValidator::new(
+        ShaderType::Compute,
+        ShaderSpec::Gles31,
+        Output::Essl,
+        &resources,
+    )
+    .unwrap();
+    assert_eq!(compiler.shader_type(), ShaderType::Compute);
+    compiler
+        .compile(
+            &["#version 310 es
+layout(local_size_x = 8, local_size_y = 4) in;
+shared vec4 tile[32];
+void main() {
+    tile[gl_LocalInvocationIndex] = vec4(0.0);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+    assert_eq!(compiler.compute_local_size(), Some([8, 4, 1]));
+    assert_eq!(compiler.shared_memory_size(), Some(32 * 16));
+
+    let compiler =
+        ShaderValidator::for_webgl2(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler
+        .compile(&["void main() {}"], CompileOptions::new())
+        .unwrap();
+    assert_eq!(compiler.compute_local_size(), None);
+    assert_eq!(compiler.shared_memory_size(), None);
+}
+
+#[test]
+fn test_geometry_and_tessellation_metadata() {
+    init();
+
+    let resources = BuiltInResources::builder()
+        .extension(Extension::ExtGeometryShader, true)
+        .extension(Extension::ExtTessellationShader, true)
+        .build();
+    let new = |shader_type| {
+        ShaderValidator::new(shader_type, ShaderSpec::Gles31, Output::Essl, &resources).unwrap()
+    };
+
+    let geometry = new(ShaderType::Geometry);
+    geometry
+        .compile(
+            &["#version 310 es
+#extension GL_EXT_geometry_shader : require
+layout(triangles, invocations = 2) in;
+layout(line_strip, max_vertices = 4) out;
+void main() {
+    gl_Position = gl_in[0].gl_Position;
+    EmitVertex();
+}"],
+            CompileOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(
+        geometry.geometry_input_primitive(),
+        Some(GeometryPrimitive::Triangles)
+    );
+    assert_eq!(
+        geometry.geometry_output_primitive(),
+        Some(GeometryPrimitive::LineStrip)
+    );
+    assert_eq!(geometry.geometry_invocations(), Some(2));
+    assert_eq!(geometry.geometry_max_vertices(), Some(4));
+    assert_eq!(geometry.tess_gen_mode(), None);
+
+    let control = new(ShaderType::TessControl);
+    control
+        .compile(
+            &["#version 310 es
+#extension GL_EXT_tessellation_shader : require
+layout(vertices = 3) out;
+void main() {
+    gl_TessLevelInner[0] = 1.0;
+}"],
+            CompileOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(control.tess_control_vertices(), Some(3));
+
+    let evaluation = new(ShaderType::TessEvaluation);
+    evaluation
+        .compile(
+            &["#version 310 es
+#extension GL_EXT_tessellation_shader : require
+layout(quads, fractional_odd_spacing, cw) in;
+void main() {
+    gl_Position = vec4(gl_TessCoord, 1.0);
+}"],
+            CompileOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(evaluation.tess_gen_mode(), Some(TessGenMode::Quads));
+    assert_eq!(
+        evaluation.tess_gen_spacing(),
+        Some(TessGenSpacing::FractionalOdd)
+    );
+    assert_eq!(
+        evaluation.tess_gen_vertex_order(),
+        Some(TessGenVertexOrder::Cw)
+    );
+    assert_eq!(evaluation.tess_gen_point_mode(), Some(false));
+    assert_eq!(evaluation.geometry_invocations(), None);
+    assert_eq!(TessGenMode::Quads.to_gl_enum(), 0x0007);
+}
+
+#[test]
+fn test_program_linker() {
+    init();
+
+    let resources = BuiltInResources::default();
+    let compile = |shader_type, source| {
+        let validator = ShaderValidator::for_webgl2(shader_type, Output::Essl, &resources).unwrap();
+        validator
+            .compile(&[source], CompileOptions::new().variables(true))
+            .unwrap();
+        validator
+    };
+
+    let vertex = compile(
+        ShaderType::Vertex,
+        "#version 300 es
+in vec4 position;
+uniform mat4 transform;
+uniform Lights { vec4 color; };
+flat out int id;
+out vec2 uv;
+void main() {
+    id = 1;
+    uv = position.xy;
+    gl_Position = transform * position * color;
+}",
+    );
+    let fragment = compile(
+        ShaderType::Fragment,
+        "#version 300 es
+precision mediump float;
+uniform highp mat4 transform;
+uniform float exposure;
+uniform Lights { highp vec4 color; };
+flat in int id;
+in vec2 uv;
+out vec4 result;
+void main() {
+    result = transform[0] * float(id) * exposure * color + vec4(uv, 0.0, 0.0);
+}",
+    );
+
+    let program = ProgramLinker::new(&vertex, &fragment).link().unwrap();
+    let uniforms: Vec<_> = program.uniforms.iter().map(|u| &*u.name).collect();
+    assert_eq!(uniforms, ["transform", "exposure"]);
+    assert_eq!(program.uniform_blocks.len(), 1);
+    assert_eq!(program.varyings.len(), 2);
+    assert_eq!(program.attributes[0].name, "position");
+    assert_eq!(program.output_variables[0].name, "result");
+
+    let mismatched = compile(
+        ShaderType::Fragment,
+        "#version 300 es
+precision mediump float;
+uniform vec4 transform;
+uniform Lights { vec3 color; };
+in vec2 id;
+in vec3 normal;
+out vec4 result;
+void main() {
+    result = transform * color.x + vec4(id, normal.xy);
+}",
+    );
+    let errors = ProgramLinker::new(&vertex, &mismatched).link().unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            LinkError::VaryingMismatch { name: "id".into() },
+            LinkError::MissingVarying {
+                name: "normal".into()
+            },
+            LinkError::UniformMismatch {
+                name: "transform".into()
+            },
+            LinkError::UniformBlockMismatch {
+                name: "Lights".into()
+            },
+        ]
+    );
+
+    let errors = ProgramLinker::new(&fragment, &vertex).link().unwrap_err();
+    assert_eq!(
+        errors[0],
+        LinkError::WrongShaderType {
+            expected: ShaderType::Vertex,
+            found: ShaderType::Fragment,
+        }
+    );
+}
+
+#[test]
+fn test_pack_varyings() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl2(
+        ShaderType::Vertex,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+struct S { vec3 a; float b; };
+out vec4 v[2];
+out mat2 m;
+out vec3 n;
+out vec2 uv[2];
+out S s;
+out float f;
+void main() {
+    v[0] = v[1] = vec4(0.0);
+    m = mat2(1.0);
+    n = vec3(0.0);
+    uv[0] = uv[1] = vec2(0.0);
+    s = S(vec3(0.0), 0.0);
+    f = 0.0;
+    gl_Position = vec4(0.0);
+}"],
+            CompileOptions::new().variables(true),
+        )
+        .unwrap();
+    let varyings: Vec<_> = compiler
+        .output_varyings()
+        .into_iter()
+        .filter(|v| !v.is_built_in())
+        .collect();
+
+    let packed = pack_varyings(&varyings, 8).unwrap();
+    let find = |name: &str| {
+        let p = packed.iter().find(|p| p.name == name).unwrap();
+        (p.row, p.column, p.rows, p.components)
+    };
+    // Spec order: mat2 before vec4, then vec3, vec2 and scalars.
+    assert_eq!(find("m"), (0, 0, 2, 4));
+    assert_eq!(find("v"), (2, 0, 2, 4));
+    assert_eq!(find("n"), (4, 0, 1, 3));
+    assert_eq!(find("s.a"), (5, 0, 1, 3));
+    assert_eq!(find("uv"), (6, 0, 2, 2));
+    let mut scalars = [find("s.b"), find("f")];
+    scalars.sort();
+    assert_eq!(scalars, [(6, 2, 1, 1), (7, 2, 1, 1)]);
+
+    // ANGLE decides whether they fit, and the port must place them whenever
+    // it does.
+    for max_vectors in 0..16 {
+        assert_eq!(
+            pack_varyings(&varyings, max_vectors).is_some(),
+            max_vectors >= 8
+        );
+    }
+
+    // Arrays of structs are packed member by member.
+    let mut lights = varyings.into_iter().find(|v| v.name == "s").unwrap();
+    lights.name = "lights".into();
+    lights.array_sizes = vec![2];
+    let names: Vec<_> = pack_varyings(&[lights.clone()], 2)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(
+        names,
+        ["lights[0].a", "lights[1].a", "lights[0].b", "lights[1].b"]
+    );
+
+    // Element counts that overflow don't fit rather than panic or wrap.
+    lights.array_sizes = vec![1 << 16, 1 << 16];
+    assert_eq!(lights.array_size_product(), u32::MAX);
+    assert_eq!(pack_varyings(&[lights.clone()], u32::MAX), None);
+    lights.array_sizes = vec![1 << 30];
+    lights.fields[0].array_sizes = vec![4];
+    assert_eq!(pack_varyings(&[lights], u32::MAX), None);
+}
+
+#[test]
+fn test_name_hasher() {
+    use std::collections::hash_map::RandomState;
+
+    init();
+
+    let source = "precision mediump float;
+uniform vec4 color;
+void main() {
+    gl_FragColor = color;
+}";
+    let compile = |hasher: NameHasher| {
+        let compiler = ShaderValidator::with_name_hasher(
+            ShaderType::Fragment,
+            ShaderSpec::WebGL,
+            Output::Essl,
+            &BuiltInResources::default(),
+            hasher,
+        )
+        .unwrap();
+        compiler
+            .compile(&[source], CompileOptions::mozangle())
+            .unwrap();
+        (compiler.object_code(), compiler.name_hashing_map())
+    };
+
+    let (object_code, map) = compile(NameHasher::new(|name| name.len() as u64));
+    assert_eq!(map["color"], "webgl_5");
+    assert!(object_code.contains("uniform mediump vec4 webgl_5;"));
+
+    // Different states give different names, clones of one the same ones.
+    let state = RandomState::new();
+    let first = compile(NameHasher::from_build_hasher(state.clone())).1;
+    assert_eq!(first, compile(NameHasher::from_build_hasher(state)).1);
+    assert_ne!(
+        first["color"],
+        compile(NameHasher::from_build_hasher(RandomState::new())).1["color"]
+    );
+}
+
+#[test]
+fn test_name_map() {
+    init();
+
+    let compiler = ShaderValidator::for_webgl2(
+        ShaderType::Vertex,
+        Output::Essl,
+        &BuiltInResources::default(),
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 300 es
+struct Light { vec3 position; float intensity; };
+uniform Light lights[2];
+uniform Transform { mat4 mvp; } transform;
+uniform Material { vec4 albedo; };
+in vec4 position;
+out vec4 v_color;
+void main() {
+    v_color = albedo * lights[1].intensity;
+    gl_Position = transform.mvp * position + vec4(lights[0].position, 0.0);
+}"],
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+
+    let map = compiler.name_map();
+    assert_eq!(map.mapped_name("position").unwrap(), "_uposition");
+    assert_eq!(map.mapped_name("v_color").unwrap(), "_uv_color");
+    assert_eq!(
+        map.mapped_name("lights[1].intensity").unwrap(),
+        "_ulights[1]._uintensity"
+    );
+    assert_eq!(
+        map.original_name("_ulights[1]._uintensity").unwrap(),
+        "lights[1].intensity"
+    );
+    assert_eq!(map.mapped_name("Transform").unwrap(), "_uTransform");
+    assert_eq!(
+        map.mapped_name("Transform.mvp").unwrap(),
+        "_uTransform._umvp"
+    );
+    assert_eq!(map.original_name("_ualbedo").unwrap(), "albedo");
+    assert_eq!(map.mapped_struct_name("Light"), None);
+    assert_eq!(map.mapped_name("gl_Position").unwrap(), "gl_Position");
+    assert!(map.original_name("lights").is_none());
+}
+
+#[test]
+fn test_name_map_shader_io_blocks() {
+    init();
+
+    let resources = BuiltInResources::builder()
+        .extension(Extension::ExtShaderIoBlocks, true)
+        .build();
+    let compiler = ShaderValidator::new(
+        ShaderType::Vertex,
+        ShaderSpec::Gles31,
+        Output::Essl,
+        &resources,
+    )
+    .unwrap();
+    compiler
+        .compile(
+            &["#version 310 es
+#extension GL_EXT_shader_io_blocks : require
+out VertexOut { vec4 color; } vout;
+void main() {
+    vout.color = vec4(1.0);
+    gl_Position = vec4(0.0);
+}"],
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+
+    let map = compiler.name_map();
+    assert_eq!(map.mapped_struct_name("VertexOut"), Some("_uVertexOut"));
+    assert_eq!(map.original_struct_name("_uVertexOut"), Some("VertexOut"));
+    assert_eq!(map.mapped_name("vout").unwrap(), "_uvout");
+    assert_eq!(
+        map.original_name("_uVertexOut._ucolor").unwrap(),
+        "VertexOut.color"
+    );
+}
+
+#[test]
+fn test_translate_driver_log() {
+    init();
+
+    let source = "precision mediump float;
+uniform vec4 tint;
+float brighten(float x) {
+    return x * 2.0;
+}
+void main() {
+    gl_FragColor = tint * brighten(0.5);
+}";
+    let resources = BuiltInResources::default();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler
+        .compile(&[source], CompileOptions::mozangle())
+        .unwrap();
+    // Only names the compiler mapped are translated, and GL driver
+    // locations are lines of the object code, which can't be traced back.
+    assert_eq!(
+        compiler.translate_driver_log(
+            "ERROR: 0:3: '_ubrighten' : no matching overload for '_utint' (vec4)\n\
+             ERROR: 0:9: '_uother' : undeclared identifier"
+        ),
+        "ERROR: 0:3: 'brighten' : no matching overload for 'tint' (vec4)\n\
+         ERROR: 0:9: '_uother' : undeclared identifier"
+    );
+
+    // The D3D compiler numbers lines after the line directives.
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Hlsl41, &resources).unwrap();
+    compiler
+        .compile_named_with_source_map(
+            &[
+                (
+                    "common.glsl",
+                    "precision mediump float;\nuniform vec4 tint;",
+                ),
+                (
+                    "main.frag",
+                    "void main() {\n    gl_FragColor = tint * 2.0;\n}",
+                ),
+            ],
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+    assert_eq!(
+        compiler.translate_driver_log(
+            "C:\\fakepath(4,20-23): error X3004: undeclared identifier '_tint'
+C:\\fakepath(1,1): warning X3571: pow(f, e) will not work for negative f
+note: 10(4): at 12:30"
+        ),
+        "main.frag(2,20-23): error X3004: undeclared identifier 'tint'
+common.glsl(1,1): warning X3571: pow(f, e) will not work for negative f
+note: 10(4): at 12:30"
+    );
+}
+
+#[test]
+fn test_source_map() {
+    init();
+
+    let common = "precision mediump float;
+float brighten(float x) {
+    return x * 2.0;
+}
+";
+    let main = "uniform vec4 tint;
+void main() {
+    gl_FragColor = tint * brighten(0.5);
+}";
+    let resources = BuiltInResources::default();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Hlsl41, &resources).unwrap();
+    compiler
+        .compile_with_source_map(&[common, main], CompileOptions::mozangle())
+        .unwrap();
+
+    let object_code: Vec<_> = compiler.object_code().lines().map(String::from).collect();
+    let source_map = compiler.source_map();
+    let find = |text: &str| {
+        let line = 1 + object_code.iter().position(|l| l.contains(text)).unwrap();
+        source_map.lookup(line as u32).unwrap()
+    };
+    assert_eq!(find("* 2.0"), SourceLocation { string: 0, line: 3 });
+    assert_eq!(find("gl_Color[0] ="), SourceLocation { string: 1, line: 3 });
+    assert_eq!(source_map.lookup(1), None);
+
+    let error = compiler
+        .compile_with_source_map(
+            &[common, "void main() {\n    undefined();\n}"],
+            CompileOptions::mozangle(),
+        )
+        .unwrap_err();
+    assert_eq!(error.diagnostics()[0].source_string, Some(1));
+    assert_eq!(error.diagnostics()[0].line, Some(2));
+
+    // Strings without a final newline still start on lines of their own.
+    let common = common.trim_end();
+    compiler
+        .compile_with_source_map(&[common, main], CompileOptions::mozangle())
+        .unwrap();
+    let object_code: Vec<_> = compiler.object_code().lines().map(String::from).collect();
+    let source_map = compiler.source_map();
+    let find = |text: &str| {
+        let line = 1 + object_code.iter().position(|l| l.contains(text)).unwrap();
+        source_map.lookup(line as u32).unwrap()
+    };
+    assert_eq!(find("* 2.0"), SourceLocation { string: 0, line: 3 });
+    assert_eq!(find("gl_Color[0] ="), SourceLocation { string: 1, line: 3 });
+    let error = compiler
+        .compile_with_source_map(
+            &[common, "void main() {\n    undefined();\n}"],
+            CompileOptions::mozangle(),
+        )
+        .unwrap_err();
+    assert_eq!(error.diagnostics()[0].source_string, Some(1));
+    assert_eq!(error.diagnostics()[0].line, Some(2));
+
+    // ANGLE only writes line directives for HLSL.
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    let error = compiler
+        .compile_with_source_map(&[common, main], CompileOptions::mozangle())
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        CompileError::SourceMapUnsupported {
+            output: Output::Essl
+        }
+    ));
+}
+
+#[test]
+fn test_compile_named() {
+    init();
+
+    let common = (
+        "common.glsl",
+        "precision mediump float;\nuniform vec4 tint;\n",
+    );
+    let lighting = (
+        "lighting.frag",
+        "void main() {\n    gl_FragColor = tint * undefined;\n}",
+    );
+    let resources = BuiltInResources::default();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Hlsl41, &resources).unwrap();
+
+    let error = compiler
+        .compile_named(&[common, lighting], CompileOptions::mozangle())
+        .unwrap_err();
+    let diagnostic = &error.diagnostics()[0];
+    assert_eq!(diagnostic.source_string, Some(1));
+    assert_eq!(diagnostic.source_name.as_deref(), Some("lighting.frag"));
+    assert_eq!(diagnostic.line, Some(2));
+    assert!(
+        error
+            .info_log()
+            .unwrap()
+            .starts_with("ERROR: lighting.frag:2: 'undefined' : "),
+        "{}",
+        error.info_log().unwrap()
+    );
+
+    let lighting = (
+        "lighting.frag",
+        "void main() {\n    gl_FragColor = tint * 2.0;\n}",
+    );
+    compiler
+        .compile_named_with_source_map(&[common, lighting], CompileOptions::mozangle())
+        .unwrap();
+    let object_code = compiler.object_code();
+    let line = 1 + object_code
+        .lines()
+        .position(|line| line.contains("gl_Color[0] ="))
+        .unwrap();
+    let source_map = compiler.source_map();
+    let location = source_map.lookup(line as u32).unwrap();
+    assert_eq!(
+        source_map.source_name(location.string),
+        Some("lighting.frag")
+    );
+    assert_eq!(location.line, 2);
+
+    // Plain compilations forget the names.
+    compiler
+        .compile(&[common.1, lighting.1], CompileOptions::mozangle())
+        .unwrap();
+    assert_eq!(compiler.source_map().source_name(0), None);
+}
+
+#[test]
+fn test_compile_with_includes() {
+    use std::collections::HashMap;
+
+    init();
+
+    let mut files = HashMap::new();
+    files.insert(
+        "common.glsl".to_owned(),
+        "#ifndef COMMON\n#define COMMON\nprecision mediump float;\n#include \"light.glsl\"\n#endif\n"
+            .to_owned(),
+    );
+    files.insert(
+        "light.glsl".to_owned(),
+        "#pragma once\n#include \"common.glsl\"\nuniform vec4 light;\n".to_owned(),
+    );
+    files.insert(
+        "broken.glsl".to_owned(),
+        "#pragma once\nvec4 broken() {\n    return undefined;\n}\n".to_owned(),
+    );
+    files.insert("a.glsl".to_owned(), "#include \"b.glsl\"\n".to_owned());
+    files.insert("b.glsl".to_owned(), "#include \"a.glsl\"\n".to_owned());
+
+    let resources = BuiltInResources::default();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler
+        .compile_with_includes(
+            "main.frag",
+            "#include \"common.glsl\"\n#include \"light.glsl\"\n#include <common.glsl>\nvoid main() {\n    gl_FragColor = light;\n}",
+            &mut files,
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+
+    let error = compiler
+        .compile_with_includes(
+            "main.frag",
+            "#include \"common.glsl\"\n#include \"broken.glsl\"\nvoid main() {\n    gl_FragColor = broken() + missing;\n}",
+            &mut files,
+            CompileOptions::mozangle(),
+        )
+        .unwrap_err();
+    let mut locations: Vec<_> = error
+        .diagnostics()
+        .iter()
+        .map(|d| (d.source_name.clone().unwrap(), d.line.unwrap()))
+        .collect();
+    locations.dedup();
+    assert_eq!(
+        locations,
+        [("broken.glsl".to_owned(), 3), ("main.frag".to_owned(), 4)]
+    );
+
+    let error = expand_includes(&mut files, "main.frag", "#include \"a.glsl\"").unwrap_err();
+    assert_eq!(
+        error,
+        IncludeError::Cycle {
+            files: vec!["a.glsl".into(), "b.glsl".into(), "a.glsl".into()]
+        }
+    );
+    match compiler.compile_with_includes(
+        "main.frag",
+        "\n#include \"missing.glsl\"",
+        &mut files,
+        CompileOptions::mozangle(),
+    ) {
+        Err(CompileError::Include(IncludeError::Unresolved { path, line, .. })) => {
+            assert_eq!(path, "missing.glsl");
+            assert_eq!(line, 2);
+        }
+        result => panic!("{:?}", result.err()),
+    }
+
+    // Includes in comments are ignored, and those in conditional blocks are
+    // left to the compiler when they can't be resolved.
+    let source = "/* Disabled:
+#include \"missing.glsl\"
+*/
+#if defined(GL_ES) && 0
+#include \"missing.glsl\"
+#endif
+precision mediump float;
+void main() {
+    gl_FragColor = vec4(0.0);
+}";
+    compiler
+        .compile_with_includes("main.frag", source, &mut files, CompileOptions::mozangle())
+        .unwrap();
+    let error = compiler
+        .compile_with_includes(
+            "main.frag",
+            &source.replace("&& 0", "&& 1"),
+            &mut files,
+            CompileOptions::mozangle(),
+        )
+        .unwrap_err();
+    let diagnostic = &error.diagnostics()[0];
+    assert_eq!(diagnostic.source_name.as_deref(), Some("main.frag"));
+    assert_eq!(diagnostic.line, Some(5));
+    assert!(
+        diagnostic.message.contains("cannot include missing.glsl"),
+        "{:?}",
+        diagnostic
+    );
+
+    // With the `source_path` option, the name is the path.
+    compiler
+        .compile_with_includes(
+            "main.frag",
+            "#extension GL_OES_standard_derivatives : enable\n#include \"common.glsl\"\nvoid main() {\n    gl_FragColor = light;\n}",
+            &mut files,
+            CompileOptions::mozangle().source_path(true),
+        )
+        .unwrap();
+    assert!(compiler.object_code().contains("light"));
+    let requested = compiler.requested_extensions();
+    assert_eq!(requested.len(), 1);
+    assert_eq!(requested[0].source_name.as_deref(), Some("main.frag"));
+
+    // Files included in skipped blocks still count as lines, and can be
+    // included again after them.
+    files.insert(
+        "fancy.glsl".to_owned(),
+        "#pragma once\nprecision mediump float;\nvec4 fancy() {\n    return vec4(1.0);\n}\n"
+            .to_owned(),
+    );
+    for &include in &["common.glsl", "fancy.glsl"] {
+        let source = format!(
+            "#if 0\n#include \"{0}\"\n#else\nfloat x = missing;\n#endif\n#include \"{0}\"\n#include \"fancy.glsl\"\nvoid main() {{\n    gl_FragColor = fancy() + missing;\n}}",
+            include
+        );
+        let error = compiler
+            .compile_with_includes("main.frag", &source, &mut files, CompileOptions::mozangle())
+            .unwrap_err();
+        let mut locations: Vec<_> = error
+            .diagnostics()
+            .iter()
+            .map(|d| (d.source_name.clone().unwrap(), d.line.unwrap()))
+            .collect();
+        locations.dedup();
+        assert_eq!(
+            locations,
+            [("main.frag".to_owned(), 4), ("main.frag".to_owned(), 9)],
+            "{}",
+            include
+        );
+    }
+}
+
+#[test]
+fn test_preprocessor() {
+    let mut preprocessor = Preprocessor::new(ShaderSpec::WebGL2);
+    preprocessor
+        .define("GL_OES_standard_derivatives", "1")
+        .unwrap();
+    let output = preprocessor.preprocess(&[
+        "#version 300 es
+#extension GL_OES_standard_derivatives : enable
+#pragma optimize(off)
+#define SCALE 2.0
+#define MUL(a, b) ((a) * (b))
+",
+        "#ifdef GL_OES_standard_derivatives
+float f = MUL(x, SCALE);
+
+#else
+float g;
+#endif
+",
+    ]);
+
+    assert!(!output.has_errors(), "{:?}", output.diagnostics);
+    assert_eq!(output.text(), "float f = ((x) * (2.0));");
+    let f = &output.tokens[1];
+    assert_eq!(f.kind, TokenKind::Identifier);
+    assert_eq!(f.location, SourceLocation { string: 1, line: 2 });
+
+    assert_eq!(
+        output.directives,
+        [
+            Directive::Version {
+                location: SourceLocation { string: 0, line: 1 },
+                version: 300,
+            },
+            Directive::Extension {
+                location: SourceLocation { string: 0, line: 2 },
+                name: "GL_OES_standard_derivatives".into(),
+                behavior: "enable".into(),
+            },
+            Directive::Pragma {
+                location: SourceLocation { string: 0, line: 3 },
+                name: "optimize".into(),
+                value: "off".into(),
+                stdgl: false,
+            },
+        ]
+    );
+
+    let mul = output.macros.iter().find(|m| m.name == "MUL").unwrap();
+    assert_eq!(mul.parameters, Some(vec!["a".to_owned(), "b".to_owned()]));
+    assert_eq!(mul.replacement, "((a) * (b))");
+    assert!(!mul.predefined);
+    let scale = output.macros.iter().find(|m| m.name == "SCALE").unwrap();
+    assert_eq!(scale.parameters, None);
+    let version = output
+        .macros
+        .iter()
+        .find(|m| m.name == "__VERSION__")
+        .unwrap();
+    assert_eq!(version.replacement, "300");
+    assert!(version.predefined);
+
+    let mut preprocessor = Preprocessor::new(ShaderSpec::WebGL);
+    preprocessor
+        .define("EMPTY", "")
+        .unwrap()
+        .define("HALF", "0.5")
+        .unwrap()
+        .define("LERP(a, b, t)", "mix(a, b, t)")
+        .unwrap()
+        .define("NOTHING()", "HALF")
+        .unwrap();
+    let output = preprocessor.preprocess(&["EMPTY float h = LERP(HALF, 1.0, NOTHING());\n"]);
+    assert!(!output.has_errors(), "{:?}", output.diagnostics);
+    assert_eq!(output.text(), "float h = mix(0.5, 1.0, 0.5);");
+    let lerp = output.macros.iter().find(|m| m.name == "LERP").unwrap();
+    assert_eq!(
+        lerp.parameters,
+        Some(vec!["a".to_owned(), "b".to_owned(), "t".to_owned()])
+    );
+    assert_eq!(lerp.replacement, "mix(a, b, t)");
+    assert!(lerp.predefined);
+
+    // Macros the shader redefines are its own.
+    let output = preprocessor.preprocess(&["#undef HALF\n#define HALF 0.25\n"]);
+    let half = output.macros.iter().find(|m| m.name == "HALF").unwrap();
+    assert_eq!(half.replacement, "0.25");
+    assert!(!half.predefined);
+
+    for &(name, replacement) in &[
+        ("", "1"),
+        ("1ONE", "1"),
+        ("NUL\0", "1"),
+        ("F(a, a)", "a"),
+        ("F(a", "a"),
+        ("F(a b)", "a"),
+        ("__LINE__", "1"),
+        ("ONE", "1\n2"),
+        ("ONE", "1 // one"),
+    ] {
+        assert!(preprocessor.define(name, replacement).is_err(), "{}", name);
+    }
+
+    let output = Preprocessor::new(ShaderSpec::WebGL).preprocess(&["#if\n#endif\n#error oops\n"]);
+    assert!(output.has_errors());
+    assert!(output
+        .diagnostics
+        .iter()
+        .any(|d| d.message == "oops" && d.line == Some(3)));
+}
+
+#[test]
+fn test_extension_behavior() {
+    init();
+
+    let resources = BuiltInResources::builder()
+        .extension(Extension::OesStandardDerivatives, true)
+        .build();
+    let compiler =
+        ShaderValidator::for_webgl(ShaderType::Fragment, Output::Essl, &resources).unwrap();
+    compiler
+        .compile(
+            &["#extension GL_OES_standard_derivatives : enable
+#extension GL_EXT_frag_depth : warn
+#ifdef GL_EXT_shader_texture_lod
+#extension GL_EXT_shader_texture_lod : enable
+#endif
+precision mediump float;
+void main() {
+    gl_FragColor = vec4(dFdx(1.0));
+}"],
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+
+    let behavior = compiler.extension_behavior();
+    assert_eq!(
+        behavior.get("GL_OES_standard_derivatives"),
+        Some(&ExtensionBehavior::Enable)
+    );
+    assert!(!behavior.contains_key("GL_EXT_frag_depth"));
+    assert!(behavior
+        .iter()
+        .filter(|&(name, _)| name != "GL_OES_standard_derivatives")
+        .all(|(_, &behavior)| !behavior.is_enabled()));
+
+    assert_eq!(
+        compiler.requested_extensions(),
+        vec![
+            RequestedExtension {
+                name: "GL_OES_standard_derivatives".into(),
+                behavior: ExtensionBehavior::Enable,
+                location: SourceLocation { string: 0, line: 1 },
+                source_name: None,
+                honoured: true,
+            },
+            RequestedExtension {
+                name: "GL_EXT_frag_depth".into(),
+                behavior: ExtensionBehavior::Warn,
+                location: SourceLocation { string: 0, line: 2 },
+                source_name: None,
+                honoured: false,
+            },
+        ]
+    );
+
+    // Names and locations follow the source strings.
+    compiler
+        .compile_named(
+            &[
+                ("prelude.frag", "precision mediump float;\n"),
+                (
+                    "main.frag",
+                    "#extension GL_OES_standard_derivatives : require
+void main() {
+    gl_FragColor = vec4(dFdy(1.0));
+}",
+                ),
+            ],
+            CompileOptions::mozangle(),
+        )
+        .unwrap();
+    let requested = compiler.requested_extensions();
+    assert_eq!(requested.len(), 1);
+    assert_eq!(requested[0].behavior, ExtensionBehavior::Require);
+    assert_eq!(requested[0].location, SourceLocation { string: 1, line: 1 });
+    assert_eq!(requested[0].source_name.as_deref(), Some("main.frag"));
+
+    // The compiler decides which directives apply, even for a shader it
+    // rejects.
+    let resources = BuiltInResources::builder()
+        .extension(Extension::ExtClipCullDistance, true)
+        .build();
+    let compiler =
+        ShaderValidator::for_webgl2(ShaderType::Vertex, Output::Essl, &resources).unwrap();
+    assert!(compiler
+        .compile(
+            &["#extension all : disable
+#extension GL_EXT_clip_cull_distance : enable
+void main() {}"],
+            CompileOptions::mozangle(),
+        )
+        .is_err());
+    let requested = compiler.requested_extensions();
+    assert_eq!(requested.len(), 2);
+    assert_eq!(requested[0].name, "all");
+    assert!(requested[0].honoured);
+    // GL_EXT_clip_cull_distance needs ESSL 3.00.
+    assert_eq!(requested[1].name, "GL_EXT_clip_cull_distance");
+    assert!(!requested[1].honoured);
+}